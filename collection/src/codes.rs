@@ -1,5 +1,6 @@
+use atlas_model::Report;
+use atlas_model::esologs::GraphQLResponse;
 use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::env;
@@ -63,15 +64,15 @@ fn load_player_map() -> io::Result<(HashMap<String,usize>, usize)> {
         let file = File::open(PLAYER_TABLE)?;
         let mut rdr = BufReader::new(file).lines();
 
-        if let Some(Ok(header)) = rdr.next() {
-            if header.trim() != "id,player_name" {
-                let parts: Vec<_> = header.trim().splitn(2, ',').collect();
-                if parts.len() == 2 {
-                    if let Ok(id) = parts[0].parse() {
-                        map.insert(parts[1].to_string(), id);
-                        max_id = max_id.max(id);
-                    }
-                }
+        if let Some(Ok(header)) = rdr.next()
+            && header.trim() != "id,player_name"
+        {
+            let parts: Vec<_> = header.trim().splitn(2, ',').collect();
+            if parts.len() == 2
+                && let Ok(id) = parts[0].parse()
+            {
+                map.insert(parts[1].to_string(), id);
+                max_id = max_id.max(id);
             }
         }
 
@@ -148,10 +149,10 @@ fn process_codes() -> Result<(), Box<dyn std::error::Error>> {
         let pm = Arc::clone(&player_map);
         ctrlc::set_handler(move || {
             eprintln!("Caught Ctrl+C! Dumping player map…");
-            if let Ok(map) = pm.lock() {
-                if let Err(e) = dump_player_map(&*map) {
-                    eprintln!("  Failed to dump: {}", e);
-                }
+            if let Ok(map) = pm.lock()
+                && let Err(e) = dump_player_map(&map)
+            {
+                eprintln!("  Failed to dump: {}", e);
             }
             std::process::exit(1);
         }).expect("Error setting Ctrl-C handler");
@@ -164,32 +165,33 @@ fn process_codes() -> Result<(), Box<dyn std::error::Error>> {
         println!("Fetching report {}", code);
         match fetch_report_data(&client, &token, code) {
             Ok(Some(report)) => {
-                if let Some(report_for_actors) = report.data.report_data.report {
-                    if let Some(players) = report_for_actors.master_data.actors {
-                        let mut id_list = Vec::with_capacity(players.len());
-                        for actor in players {
-                            let name = actor.display_name;
-                            if name == "nil" { continue; }
-                            let mut map_lock = player_map.lock().unwrap();
-                            let id = *map_lock.entry(name.clone())
-                                .or_insert_with(|| {
-                                    let id = next_player_id;
-                                    next_player_id += 1;
-                                    id
-                                });
-                            drop(map_lock);
-                            id_list.push(id);
-                        }
-                        let obj = json!({
-                            "code": code,
-                            "endTime": report_for_actors.end_time,
-                            "startTime": report_for_actors.start_time,
-                            "players": id_list
-                        });
-                        println!("Adding {}", obj);
-
-                        append_line(REPORTS_OUT, &obj.to_string())?;
+                if let Some(report_for_actors) = report.data.report_data.report
+                    && let Some(players) = report_for_actors.master_data.actors
+                {
+                    let mut id_list = Vec::with_capacity(players.len());
+                    for actor in players {
+                        let name = actor.display_name;
+                        if name == "nil" { continue; }
+                        let mut map_lock = player_map.lock().unwrap();
+                        let id = *map_lock.entry(name.clone())
+                            .or_insert_with(|| {
+                                let id = next_player_id;
+                                next_player_id += 1;
+                                id
+                            });
+                        drop(map_lock);
+                        id_list.push(id);
                     }
+                    let obj = Report {
+                        start_time: report_for_actors.start_time,
+                        end_time: report_for_actors.end_time,
+                        players: id_list,
+                        code: code.clone(),
+                    };
+                    let line = serde_json::to_string(&obj)?;
+                    println!("Adding {}", line);
+
+                    append_line(REPORTS_OUT, &line)?;
                 }
             }
             Ok(None) => {
@@ -205,14 +207,14 @@ fn process_codes() -> Result<(), Box<dyn std::error::Error>> {
 
         if since_last_checkpoint >= CHECKPOINT_INTERVAL {
             println!("Periodic checkpoint: dumping player map");
-            dump_player_map(&*player_map.lock().unwrap())?;
+            dump_player_map(&player_map.lock().unwrap())?;
 
             since_last_checkpoint = 0;
         }
     }
 
     println!("Completed processing all codes.");
-    dump_player_map(&*player_map.lock().unwrap())?;
+    dump_player_map(&player_map.lock().unwrap())?;
     Ok(())
 }
 
@@ -220,41 +222,3 @@ pub fn process_codes_main_function() -> Result<(), Box<dyn std::error::Error>> {
     process_codes()?;
     Ok(())
 }
-
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GraphQLResponse {
-    pub data: ReportDataWrapper2,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportDataWrapper2 {
-    #[serde(rename = "reportData")]
-    pub report_data: ReportWrapper,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportWrapper {
-    pub report: Option<ReportForActors>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportForActors {
-    #[serde(rename = "endTime")]
-    pub end_time: u64,
-    #[serde(rename = "startTime")]
-    pub start_time: u64,
-    #[serde(rename = "masterData")]
-    pub master_data: MasterData,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MasterData {
-    pub actors: Option<Vec<Actor>>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Actor {
-    #[serde(rename = "displayName")]
-    pub display_name: String,
-}
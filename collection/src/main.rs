@@ -1,5 +1,4 @@
 // use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
 // use serde_json::json;
 // use std::env;
 // use std::fs::{File, OpenOptions};
@@ -175,82 +174,3 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     codes::process_codes_main_function()?;
     Ok(())
 }
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Root {
-    pub data: ReportDataWrapper,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportDataWrapper {
-    #[serde(rename = "reportData")]
-    pub report_data: ReportsWrapper,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportsWrapper {
-    pub reports: Option<ReportsData>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportsData {
-    pub data: Vec<Report>,
-    pub has_more_pages: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Report {
-    pub code: String,
-}
-
-// {
-//   reportData {
-//     report(code: "pb9zkYmVnha4tJQR") {
-// 		 endTime,
-// 		 startTime,
-//       masterData(translate: false) {
-//         actors(type: "Player") {
-//           displayName
-//         }
-//       }
-//     }
-//   }
-// }
-
-
-#[derive(Debug, Deserialize)]
-pub struct GraphQLResponse {
-    pub data: ReportDataContainer,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ReportDataContainer {
-    #[serde(rename = "reportData")]
-    pub report_data: ReportData,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ReportData {
-    pub report: Option<ReportForActors>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ReportForActors {
-    #[serde(rename = "endTime")]
-    pub end_time: u64,
-    #[serde(rename = "startTime")]
-    pub start_time: u64,
-    #[serde(rename = "masterData")]
-    pub master_data: MasterData,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct MasterData {
-    pub actors: Option<Vec<Actor>>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct Actor {
-    #[serde(rename = "displayName")]
-    pub display_name: String,
-}
\ No newline at end of file
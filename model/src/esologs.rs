@@ -0,0 +1,85 @@
+//! Responses from the esologs.com v2 GraphQL API.
+//!
+//! https://www.esologs.com/v2-api-docs/eso/report.doc.html
+
+use serde::{Deserialize, Serialize};
+
+// { reportData { reports(userID: 1, page: 1) { data { code } has_more_pages } } }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportListResponse {
+    pub data: ReportListData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportListData {
+    #[serde(rename = "reportData")]
+    pub report_data: ReportsWrapper,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportsWrapper {
+    pub reports: Option<ReportsPage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportsPage {
+    pub data: Vec<ReportCode>,
+    pub has_more_pages: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportCode {
+    pub code: String,
+}
+
+// {
+//   reportData {
+//     report(code: "pb9zkYmVnha4tJQR") {
+//       endTime,
+//       startTime,
+//       masterData(translate: false) {
+//         actors(type: "Player") {
+//           displayName
+//         }
+//       }
+//     }
+//   }
+// }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphQLResponse {
+    pub data: ReportDataContainer,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportDataContainer {
+    #[serde(rename = "reportData")]
+    pub report_data: ReportWrapper,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportWrapper {
+    pub report: Option<ReportForActors>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportForActors {
+    #[serde(rename = "endTime")]
+    pub end_time: u64,
+    #[serde(rename = "startTime")]
+    pub start_time: u64,
+    #[serde(rename = "masterData")]
+    pub master_data: MasterData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MasterData {
+    pub actors: Option<Vec<Actor>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Actor {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
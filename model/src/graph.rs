@@ -0,0 +1,38 @@
+//! The graph JSON read by the visualisation, in the layout produced by
+//! Gephi's JSONExporter plugin.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub key: String,
+    pub attributes: NodeAttributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeAttributes {
+    pub label: String,
+    pub x: f64,
+    pub y: f64,
+    pub size: f32,
+    pub color: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edge {
+    pub key: String,
+    pub source: String,
+    pub target: String,
+    pub attributes: EdgeAttributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeAttributes {
+    pub weight: f64,
+}
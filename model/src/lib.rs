@@ -0,0 +1,12 @@
+//! Record types shared by every stage of the atlas pipeline.
+//!
+//! `collection` writes [`Report`] lines and [`Player`] rows, `processing` reads
+//! them back and writes a [`graph::Graph`], which `visualisation` renders.
+//! Keeping the shapes in one place means a field change shows up as a compile
+//! error in every stage instead of a runtime parse failure.
+
+pub mod esologs;
+pub mod graph;
+mod records;
+
+pub use records::{Player, Report};
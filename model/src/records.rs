@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// One row of `player_table.csv`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Player {
+    pub id: usize,
+    pub player_name: String,
+}
+
+/// One line of `report_details.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    #[serde(rename = "startTime")]
+    pub start_time: u64,
+    #[serde(rename = "endTime")]
+    pub end_time: u64,
+    pub players: Vec<usize>,
+    pub code: String,
+}
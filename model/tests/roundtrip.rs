@@ -0,0 +1,125 @@
+use atlas_model::esologs::{GraphQLResponse, ReportListResponse};
+use atlas_model::graph::{Edge, EdgeAttributes, Graph, Node, NodeAttributes};
+use atlas_model::{Player, Report};
+
+#[test]
+fn report_line_round_trip() {
+    let line = r#"{"code":"pb9zkYmVnha4tJQR","endTime":1700003600000,"players":[4,8,15],"startTime":1700000000000}"#;
+    let report: Report = serde_json::from_str(line).unwrap();
+    assert_eq!(
+        report,
+        Report {
+            start_time: 1_700_000_000_000,
+            end_time: 1_700_003_600_000,
+            players: vec![4, 8, 15],
+            code: "pb9zkYmVnha4tJQR".to_string(),
+        }
+    );
+
+    let written = serde_json::to_string(&report).unwrap();
+    assert!(written.contains(r#""startTime":1700000000000"#));
+    assert!(written.contains(r#""endTime":1700003600000"#));
+    assert_eq!(serde_json::from_str::<Report>(&written).unwrap(), report);
+}
+
+#[test]
+fn player_table_round_trip() {
+    let players = vec![
+        Player { id: 1, player_name: "@sheumais".to_string() },
+        Player { id: 2, player_name: "@with,comma".to_string() },
+    ];
+
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    for player in &players {
+        wtr.serialize(player).unwrap();
+    }
+    let bytes = wtr.into_inner().unwrap();
+    assert!(bytes.starts_with(b"id,player_name\n"));
+
+    let mut rdr = csv::Reader::from_reader(bytes.as_slice());
+    let read: Vec<Player> = rdr.deserialize().collect::<Result<_, _>>().unwrap();
+    assert_eq!(read, players);
+}
+
+#[test]
+fn graph_json_round_trip() {
+    let raw = r##"{
+        "nodes": [
+            {"key": "12", "attributes": {"label": "@a", "x": -10.5, "y": 3.25, "size": 14.0, "color": "#ff7f0e"}},
+            {"key": "40", "attributes": {"label": "@b", "x": 0.0, "y": 1.0, "size": 4.0, "color": "#1f77b4"}}
+        ],
+        "edges": [
+            {"key": "0", "source": "12", "target": "40", "attributes": {"weight": 5.5}}
+        ]
+    }"##;
+    let graph: Graph = serde_json::from_str(raw).unwrap();
+    assert_eq!(
+        graph,
+        Graph {
+            nodes: vec![
+                Node {
+                    key: "12".to_string(),
+                    attributes: NodeAttributes {
+                        label: "@a".to_string(),
+                        x: -10.5,
+                        y: 3.25,
+                        size: 14.0,
+                        color: "#ff7f0e".to_string(),
+                    },
+                },
+                Node {
+                    key: "40".to_string(),
+                    attributes: NodeAttributes {
+                        label: "@b".to_string(),
+                        x: 0.0,
+                        y: 1.0,
+                        size: 4.0,
+                        color: "#1f77b4".to_string(),
+                    },
+                },
+            ],
+            edges: vec![Edge {
+                key: "0".to_string(),
+                source: "12".to_string(),
+                target: "40".to_string(),
+                attributes: EdgeAttributes { weight: 5.5 },
+            }],
+        }
+    );
+
+    let written = serde_json::to_string(&graph).unwrap();
+    assert_eq!(serde_json::from_str::<Graph>(&written).unwrap(), graph);
+}
+
+#[test]
+fn graph_json_ignores_extra_gephi_attributes() {
+    let raw = r##"{
+        "nodes": [{"key": "1", "attributes": {"label": "@a", "x": 1.0, "y": 2.0, "size": 3.0, "color": "#000000", "modularity_class": 4}}],
+        "edges": [{"key": "0", "source": "1", "target": "1", "attributes": {"weight": 1.0, "label": ""}}]
+    }"##;
+    let graph: Graph = serde_json::from_str(raw).unwrap();
+    assert_eq!(graph.nodes[0].attributes.color, "#000000");
+    assert_eq!(graph.edges[0].attributes.weight, 1.0);
+}
+
+#[test]
+fn esologs_responses_parse() {
+    let report = r#"{"data":{"reportData":{"report":{"endTime":20,"startTime":10,"masterData":{"actors":[{"displayName":"@a"},{"displayName":"nil"}]}}}}}"#;
+    let response: GraphQLResponse = serde_json::from_str(report).unwrap();
+    let report = response.data.report_data.report.unwrap();
+    assert_eq!((report.start_time, report.end_time), (10, 20));
+    let names: Vec<_> = report
+        .master_data
+        .actors
+        .unwrap()
+        .into_iter()
+        .map(|a| a.display_name)
+        .collect();
+    assert_eq!(names, ["@a", "nil"]);
+
+    let list = r#"{"data":{"reportData":{"reports":{"data":[{"code":"abc"}],"has_more_pages":false}}}}"#;
+    let response: ReportListResponse = serde_json::from_str(list).unwrap();
+    let page = response.data.report_data.reports.unwrap();
+    assert_eq!(page.data[0].code, "abc");
+    assert!(!page.has_more_pages);
+}
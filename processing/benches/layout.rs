@@ -0,0 +1,65 @@
+//! Barnes–Hut against exact repulsion on synthetic atlases.
+//!
+//! The graphs are planted partitions shaped like the real data: groups of a
+//! dozen or so raiders with most edges inside the group and degrees around 20.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use processing::{
+    config::LayoutConfig,
+    graph::{AtlasEdge, AtlasGraph, AtlasNode},
+    layout::ForceAtlas2,
+    rng::Rng,
+};
+
+const GROUP_SIZE: usize = 15;
+const EDGES_PER_NODE: usize = 10;
+
+fn synthetic_graph(nodes: usize, seed: u64) -> AtlasGraph {
+    let mut rng = Rng::new(seed);
+    let mut graph = AtlasGraph::default();
+    for id in 0..nodes {
+        graph.nodes.push(AtlasNode { id, label: format!("@player{}", id), ..AtlasNode::default() });
+    }
+    for source in 0..nodes {
+        for _ in 0..EDGES_PER_NODE {
+            let target = if rng.next_f64() < 0.8 {
+                let group = source / GROUP_SIZE * GROUP_SIZE;
+                (group + (rng.next_u64() as usize) % GROUP_SIZE).min(nodes - 1)
+            } else {
+                (rng.next_u64() as usize) % nodes
+            };
+            if target != source {
+                let weight = 7.0 + rng.next_f64() * 2.0;
+                graph.edges.push(AtlasEdge { source, target, weight, ..AtlasEdge::default() });
+            }
+        }
+    }
+    graph.size_by_degree();
+    graph
+}
+
+fn bench_repulsion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fa2_step");
+    group.sample_size(10);
+
+    for &nodes in &[1_000, 5_000, 20_000] {
+        let graph = synthetic_graph(nodes, 7);
+        for (name, barnes_hut) in [("barnes_hut", true), ("exact", false)] {
+            if !barnes_hut && nodes > 5_000 {
+                continue;
+            }
+            let settings = LayoutConfig { barnes_hut, ..LayoutConfig::default() };
+            let mut layout = ForceAtlas2::new(&graph, &settings);
+            for _ in 0..20 {
+                layout.step();
+            }
+            group.bench_with_input(BenchmarkId::new(name, nodes), &nodes, |b, _| {
+                b.iter(|| layout.step())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_repulsion);
+criterion_main!(benches);
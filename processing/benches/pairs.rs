@@ -0,0 +1,61 @@
+//! Pair counting on one thread against several, on synthetic reports.
+//!
+//! Reports are shaped like the real data after filtering: a dozen or so
+//! raiders, mostly from the same group, out of a few thousand frequent
+//! players.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use processing::{pairs::PairCounter, rng::Rng};
+
+const PLAYERS: u64 = 6_000;
+const GROUP_SIZE: u64 = 15;
+const REPORTS: usize = 50_000;
+
+fn synthetic_reports(seed: u64) -> Vec<Vec<u32>> {
+    let mut rng = Rng::new(seed);
+    (0..REPORTS)
+        .map(|_| {
+            let group = rng.next_u64() % (PLAYERS / GROUP_SIZE) * GROUP_SIZE;
+            let size = 8 + rng.next_u64() % 12;
+            let mut players: Vec<u32> = (0..size)
+                .map(|_| {
+                    if rng.next_f64() < 0.8 {
+                        (group + rng.next_u64() % GROUP_SIZE) as u32
+                    } else {
+                        (rng.next_u64() % PLAYERS) as u32
+                    }
+                })
+                .collect();
+            players.sort_unstable();
+            players.dedup();
+            players
+        })
+        .collect()
+}
+
+fn bench_pairs(c: &mut Criterion) {
+    let reports = synthetic_reports(7);
+    let mut group = c.benchmark_group("pair_counting");
+    group.sample_size(10);
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut thread_counts = vec![1, 2, 4, cores];
+    thread_counts.sort_unstable();
+    thread_counts.dedup();
+    for threads in thread_counts {
+        group.bench_with_input(BenchmarkId::new("threads", threads), &threads, |b, &threads| {
+            b.iter(|| {
+                let mut counter = PairCounter::new(threads);
+                for players in &reports {
+                    let share = 1.0 / (players.len() as f64 - 1.0);
+                    counter.add(players.iter().copied(), 3_600_000, share);
+                }
+                counter.finish()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pairs);
+criterion_main!(benches);
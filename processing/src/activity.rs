@@ -0,0 +1,182 @@
+//! Each player's activity over time: reports, hours and distinct partners per
+//! month (or quarter, or year), for charting a raider's career and seeing
+//! when they quit or came back.
+
+use std::{collections::BTreeMap, fs::File, io::{BufWriter, Write}, path::Path};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+
+use crate::{
+    build::{self, Reports},
+    config::Config,
+    dates::{MS_PER_HOUR, Period},
+    input, query,
+};
+
+#[derive(Debug, Clone)]
+pub struct ActivityOptions<'a> {
+    pub period: Period,
+    /// Players to follow by display name. Empty follows everyone with at
+    /// least the filters' `min_appearances` valid reports.
+    pub names: &'a [String],
+    pub csv: &'a Path,
+    pub json: Option<&'a Path>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerActivity {
+    pub id: usize,
+    pub name: String,
+    /// Every period from the player's first active one to their last,
+    /// including the quiet ones in between.
+    pub periods: Vec<PeriodActivity>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodActivity {
+    pub period: String,
+    pub reports: usize,
+    /// Time in reports, capped per report like edge weights.
+    pub hours: f64,
+    /// Different players raided with.
+    pub partners: usize,
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    id: usize,
+    name: &'a str,
+    period: &'a str,
+    reports: usize,
+    hours: f64,
+    partners: usize,
+}
+
+#[derive(Default)]
+struct Bucket {
+    reports: usize,
+    duration: u64,
+    partners: FxHashSet<u32>,
+}
+
+/// Every period from the first bucket's to the last's, empty ones included.
+fn timeline(buckets: &BTreeMap<u64, Bucket>, period: Period) -> Vec<PeriodActivity> {
+    let (Some(&first), Some(&last)) = (buckets.keys().next(), buckets.keys().next_back()) else {
+        return Vec::new();
+    };
+    let mut timeline = Vec::new();
+    let mut start = first;
+    while start <= last {
+        let bucket = buckets.get(&start);
+        timeline.push(PeriodActivity {
+            period: period.label(start),
+            reports: bucket.map_or(0, |b| b.reports),
+            hours: bucket.map_or(0.0, |b| b.duration as f64 / MS_PER_HOUR as f64),
+            partners: bucket.map_or(0, |b| b.partners.len()),
+        });
+        start = period.next(start);
+    }
+    timeline
+}
+
+pub fn run(config: &Config, options: &ActivityOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let players = input::read_player_table(&config.input.players)?;
+    let reports = Reports::open(config)?;
+
+    let followed: FxHashSet<u32> = if options.names.is_empty() {
+        let appearances = build::count_appearances(&reports, config, None)?;
+        appearances
+            .players
+            .iter()
+            .filter(|(_, stats)| stats.appearances >= config.filters.min_appearances)
+            .map(|(&id, _)| id)
+            .collect()
+    } else {
+        let by_name: FxHashMap<&str, usize> = players.iter().map(|p| (p.player_name.as_str(), p.id)).collect();
+        let mut followed = FxHashSet::default();
+        for name in options.names {
+            let id = by_name
+                .get(name.as_str())
+                .ok_or_else(|| query::unknown_player(name, players.iter().map(|p| p.player_name.as_str())))?;
+            followed.insert(*id as u32);
+        }
+        followed
+    };
+    println!("Following {} players", followed.len());
+
+    let mut buckets: FxHashMap<u32, BTreeMap<u64, Bucket>> = FxHashMap::default();
+    reports.for_each_valid(config, None, |_, report, duration| {
+        let start = options.period.start_of(report.start_time);
+        for &id in &report.players {
+            let id = id as u32;
+            if !followed.contains(&id) {
+                continue;
+            }
+            let bucket = buckets.entry(id).or_default().entry(start).or_default();
+            bucket.reports += 1;
+            bucket.duration += duration;
+            bucket.partners.extend(report.players.iter().map(|&p| p as u32).filter(|&p| p != id));
+        }
+    })?;
+
+    let mut activity = Vec::with_capacity(buckets.len());
+    for player in &players {
+        let Some(periods) = buckets.get(&(player.id as u32)) else { continue };
+        let periods = timeline(periods, options.period);
+        activity.push(PlayerActivity { id: player.id, name: player.player_name.clone(), periods });
+    }
+    activity.sort_by_key(|a| a.id);
+    activity.dedup_by_key(|a| a.id);
+
+    let mut wtr = csv::Writer::from_path(options.csv)?;
+    for player in &activity {
+        for period in &player.periods {
+            wtr.serialize(Row {
+                id: player.id,
+                name: &player.name,
+                period: &period.period,
+                reports: period.reports,
+                hours: period.hours,
+                partners: period.partners,
+            })?;
+        }
+    }
+    wtr.flush()?;
+    println!("Wrote activity of {} players to {}", activity.len(), options.csv.display());
+
+    if let Some(path) = options.json {
+        let mut output = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut output, &activity)?;
+        output.flush()?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::millis_of;
+
+    #[test]
+    fn fills_quiet_periods() {
+        let bucket = |reports, hours, partners: &[u32]| Bucket {
+            reports,
+            duration: hours * MS_PER_HOUR,
+            partners: partners.iter().copied().collect(),
+        };
+        let buckets = BTreeMap::from([
+            (millis_of(2023, 11, 1), bucket(2, 3, &[7, 8])),
+            (millis_of(2024, 2, 1), bucket(1, 1, &[7])),
+        ]);
+
+        let months = timeline(&buckets, Period::Month);
+        let rows: Vec<(&str, usize, f64, usize)> =
+            months.iter().map(|p| (p.period.as_str(), p.reports, p.hours, p.partners)).collect();
+        assert_eq!(
+            rows,
+            [("2023-11", 2, 3.0, 2), ("2023-12", 0, 0.0, 0), ("2024-01", 0, 0.0, 0), ("2024-02", 1, 1.0, 1)]
+        );
+        assert!(timeline(&BTreeMap::new(), Period::Month).is_empty());
+    }
+}
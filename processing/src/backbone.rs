@@ -0,0 +1,194 @@
+//! Sparsifying the weighted edge list before it becomes the graph. The
+//! atlas keeps each player's top `top_k` edges; the other methods keep the
+//! edges that stand out statistically instead of a fixed number per player.
+
+use std::{collections::HashMap, str::FromStr};
+use serde::{Deserialize, Serialize};
+
+use crate::config::FilterConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    /// Each player's `top_k` heaviest edges.
+    TopK,
+    /// Serrano, Boguñá and Vespignani's disparity filter: an edge stays if it
+    /// holds more of either endpoint's strength than chance would give it, at
+    /// significance `alpha`.
+    Disparity,
+    /// Coscia and Neffke's noise-corrected backbone: an edge stays if its
+    /// weight beats the one expected from its endpoints' strengths by
+    /// `noise_delta` standard deviations. Meant for count-like weights.
+    NoiseCorrected,
+    /// Every edge at or above the `quantile` of all weights.
+    Quantile,
+}
+
+impl Method {
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::TopK => "top_k",
+            Method::Disparity => "disparity",
+            Method::NoiseCorrected => "noise_corrected",
+            Method::Quantile => "quantile",
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top_k" => Ok(Method::TopK),
+            "disparity" => Ok(Method::Disparity),
+            "noise_corrected" => Ok(Method::NoiseCorrected),
+            "quantile" => Ok(Method::Quantile),
+            _ => Err(format!(
+                "Unknown backbone {:?}, expected top_k, disparity, noise_corrected or quantile",
+                s
+            )),
+        }
+    }
+}
+
+/// Decides which of `edges`, given as `(player, player, weight)` with
+/// positive weights, to keep.
+pub fn extract(edges: &[(usize, usize, f64)], filters: &FilterConfig) -> Vec<bool> {
+    let keep = match filters.backbone {
+        Method::TopK => top_k(edges, filters.top_k),
+        Method::Disparity => disparity(edges, filters.alpha),
+        Method::NoiseCorrected => noise_corrected(edges, filters.noise_delta),
+        Method::Quantile => quantile(edges, filters.quantile),
+    };
+    let kept = keep.iter().filter(|&&k| k).count();
+    println!(
+        "Backbone {} kept {} of {} edges ({:.1}%)",
+        filters.backbone.name(),
+        kept,
+        edges.len(),
+        100.0 * kept as f64 / edges.len().max(1) as f64
+    );
+    keep
+}
+
+fn top_k(edges: &[(usize, usize, f64)], k: usize) -> Vec<bool> {
+    let mut per_player: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, &(a, b, _)) in edges.iter().enumerate() {
+        per_player.entry(a).or_default().push(i);
+        per_player.entry(b).or_default().push(i);
+    }
+
+    let mut keep = vec![false; edges.len()];
+    for mut incident in per_player.into_values() {
+        // Ties go to the edge listed first, so the same input keeps the same
+        // edges every run.
+        incident.sort_unstable_by(|&x, &y| edges[y].2.total_cmp(&edges[x].2).then(x.cmp(&y)));
+        for &i in incident.iter().take(k) {
+            keep[i] = true;
+        }
+    }
+    keep
+}
+
+/// Strength and degree of every player.
+fn strengths(edges: &[(usize, usize, f64)]) -> HashMap<usize, (f64, usize)> {
+    let mut strengths: HashMap<usize, (f64, usize)> = HashMap::new();
+    for &(a, b, w) in edges {
+        for p in [a, b] {
+            let s = strengths.entry(p).or_default();
+            s.0 += w;
+            s.1 += 1;
+        }
+    }
+    strengths
+}
+
+fn disparity(edges: &[(usize, usize, f64)], alpha: f64) -> Vec<bool> {
+    let strengths = strengths(edges);
+    // Probability of an edge taking at least this share of a node's
+    // strength when the strength is split uniformly at random.
+    let p_value = |node: usize, w: f64| {
+        let (strength, degree) = strengths[&node];
+        (1.0 - w / strength).powi(degree as i32 - 1)
+    };
+    edges
+        .iter()
+        .map(|&(a, b, w)| p_value(a, w) < alpha || p_value(b, w) < alpha)
+        .collect()
+}
+
+fn noise_corrected(edges: &[(usize, usize, f64)], delta: f64) -> Vec<bool> {
+    let strengths = strengths(edges);
+    // Each undirected edge appears once per direction in the adjacency
+    // matrix this is defined over.
+    let total: f64 = 2.0 * edges.iter().map(|&(_, _, w)| w).sum::<f64>();
+    edges
+        .iter()
+        .map(|&(a, b, nij)| {
+            let (ni, nj) = (strengths[&a].0, strengths[&b].0);
+            let kappa = total / (ni * nj);
+            let score = (kappa * nij - 1.0) / (kappa * nij + 1.0);
+
+            // Beta prior on the edge's share of the total, posterior after
+            // seeing `nij`, then the delta method for the score's variance.
+            let prior_mean = ni * nj / (total * total);
+            let prior_var = ni * nj * (total - ni) * (total - nj) / (total.powi(4) * (total - 1.0));
+            let alpha_prior = prior_mean * prior_mean / prior_var * (1.0 - prior_mean) - prior_mean;
+            let beta_prior = prior_mean / prior_var * (1.0 - prior_mean * prior_mean) - (1.0 - prior_mean);
+            let alpha_post = alpha_prior + nij;
+            let beta_post = total - nij + beta_prior;
+            let expected = alpha_post / (alpha_post + beta_post);
+            let var_nij = expected * (1.0 - expected) * total;
+            let d = 1.0 / (ni * nj) - total * (ni + nj) / (ni * nj).powi(2);
+            let var_score = var_nij * (2.0 * (kappa + nij * d) / (kappa * nij + 1.0).powi(2)).powi(2);
+
+            score - delta * var_score.sqrt() > 0.0
+        })
+        .collect()
+}
+
+fn quantile(edges: &[(usize, usize, f64)], q: f64) -> Vec<bool> {
+    if edges.is_empty() {
+        return Vec::new();
+    }
+    let mut weights: Vec<f64> = edges.iter().map(|&(_, _, w)| w).collect();
+    weights.sort_unstable_by(f64::total_cmp);
+    let index = ((weights.len() - 1) as f64 * q).round() as usize;
+    let cutoff = weights[index];
+    edges.iter().map(|&(_, _, w)| w >= cutoff).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A hub with one strong tie and three weak ones.
+    const STAR: [(usize, usize, f64); 4] = [(0, 1, 10.0), (0, 2, 1.0), (0, 3, 1.0), (0, 4, 1.0)];
+
+    #[test]
+    fn disparity_on_a_star() {
+        // The hub has strength 13 over 4 edges. The strong edge's p-value
+        // there is (1 - 10/13)^3 = 27/2197 ≈ 0.01229 and a weak one's is
+        // (12/13)^3 ≈ 0.787. Leaves have one edge each, so a p-value of 1.
+        assert_eq!(disparity(&STAR, 0.05), [true, false, false, false]);
+        assert_eq!(disparity(&STAR, 0.0123), [true, false, false, false]);
+        assert_eq!(disparity(&STAR, 0.0122), [false, false, false, false]);
+        assert_eq!(disparity(&STAR, 0.79), [true, true, true, true]);
+    }
+
+    #[test]
+    fn noise_corrected_on_two_groups() {
+        // Strengths are 13, 11, 2, 10 and 9, with 44 as the total of both
+        // directions. For (1, 2), kappa = 44 / (11 * 2) = 2 and the score is
+        // (2 - 1) / (2 + 1) = 1/3, with a standard deviation of about 0.160,
+        // so it stays up to delta ≈ 2.08. (0, 2) scores 0.257 ± 0.179 and
+        // goes at delta ≈ 1.44, and the hub's weak ties to 3 and 4 score
+        // below zero. The strong ties are 14 standard deviations clear.
+        let edges = [(0, 1, 10.0), (0, 2, 1.0), (0, 3, 1.0), (0, 4, 1.0), (1, 2, 1.0), (3, 4, 8.0)];
+        assert_eq!(noise_corrected(&edges, 1.4), [true, true, false, false, true, true]);
+        assert_eq!(noise_corrected(&edges, 1.64), [true, false, false, false, true, true]);
+        assert_eq!(noise_corrected(&edges, 2.1), [true, false, false, false, false, true]);
+        assert_eq!(noise_corrected(&edges, 0.0), [true, true, false, false, true, true]);
+    }
+}
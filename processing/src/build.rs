@@ -0,0 +1,338 @@
+use std::{collections::HashMap, fs::create_dir_all};
+use atlas_model::Report;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{backbone, centrality, community, config::Config, dates::format_date, dedup::{Deduplicator, Duplicates}, dynamic::Timeline, gexf, graph::{AtlasEdge, AtlasGraph, AtlasNode, DEFAULT_NODE_COLOR}, input::{self, ReportSource}, json, layout, manifest::{FileRecord, Manifest}, pairs::{EdgeKey, EdgeStats, PairCounter}, snapshot::{self, Window}, weighting::{Decay, PairStats, Weighting}};
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlayerStats {
+    pub(crate) appearances: usize,
+    pub(crate) duration: u64,
+    pub(crate) first_seen: u64,
+    pub(crate) last_seen: u64,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        PlayerStats { appearances: 0, duration: 0, first_seen: u64::MAX, last_seen: 0 }
+    }
+}
+
+/// First pass over the reports: who appears, how often and when.
+pub(crate) struct Appearances {
+    pub(crate) players: FxHashMap<u32, PlayerStats>,
+    pub(crate) reports: usize,
+    first_start: Option<u64>,
+    last_start: Option<u64>,
+}
+
+/// The report file as every pass sees it: reports over the size limit and
+/// duplicate uploads of the same raid are left out.
+pub(crate) struct Reports {
+    source: ReportSource,
+    duplicates: Duplicates,
+}
+
+impl Reports {
+    pub(crate) fn open(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let cache_dir = config.input.cache.then_some(config.input.cache_dir.as_path());
+        let rejects = config.input.lenient.then_some(config.input.rejects.as_path());
+        let source = ReportSource::open(&config.input.reports, cache_dir, rejects)?;
+        let mut reports = Reports { source, duplicates: Duplicates::default() };
+
+        if config.dedup.enabled {
+            let mut dedup = Deduplicator::default();
+            let mut error = None;
+            reports.for_each_valid(config, None, |ordinal, report, _| {
+                if let Err(e) = dedup.add(ordinal, report) {
+                    error.get_or_insert(e);
+                }
+            })?;
+            if let Some(e) = error {
+                return Err(e.into());
+            }
+            reports.duplicates = dedup.finish(config.dedup.min_overlap);
+            println!(
+                "Found {} raids uploaded more than once, skipping {} duplicate reports",
+                reports.duplicates.clusters, reports.duplicates.removed
+            );
+        }
+        Ok(reports)
+    }
+
+    /// Streams the reports that pass the report size filter, aren't
+    /// duplicates and start inside `window`, along with their position in
+    /// the file and the capped duration each one adds.
+    pub(crate) fn for_each_valid(
+        &self,
+        config: &Config,
+        window: Option<&Window>,
+        mut f: impl FnMut(usize, &Report, u64),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let filters = &config.filters;
+        let mut ordinal = 0;
+        self.source.for_each(|report| {
+            ordinal += 1;
+            if report.players.len() >= filters.max_report_players || self.duplicates.contains(ordinal - 1) {
+                return;
+            }
+            if window.is_some_and(|w| !w.contains(report.start_time)) {
+                return;
+            }
+            let duration = report.end_time.saturating_sub(report.start_time).min(filters.max_report_duration_ms);
+            f(ordinal - 1, report, duration);
+        })?;
+        Ok(())
+    }
+}
+
+pub fn run(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let id_to_name = input::read_players(&config.input.players)?;
+    println!("Successfully parsed player_table");
+
+    let reports = Reports::open(config)?;
+
+    let appearances = count_appearances(&reports, config, None)?;
+    println!("Total valid reports: {}", appearances.reports);
+
+    let mut graph = select(&reports, config, &id_to_name, &appearances, None)?;
+    finish(&mut graph, config);
+
+    let mut outputs = Vec::new();
+    println!("Creating gexf...");
+    gexf::write(&graph, &config.output.gexf)?;
+    outputs.push(config.output.gexf.clone());
+
+    if let Some(path) = &config.output.graph_json {
+        println!("Writing graph json to {}", path.display());
+        json::write(&graph, path)?;
+        outputs.push(path.clone());
+    }
+
+    if let Some(path) = &config.output.centrality_csv {
+        println!("Writing centrality scores to {}", path.display());
+        centrality::write_csv(&graph, path)?;
+        outputs.push(path.clone());
+    }
+
+    if let Some(path) = &config.output.dynamic_gexf {
+        println!("Writing dynamic gexf to {}", path.display());
+        let mut timeline = Timeline::new(&graph, config.output.dynamic_period, config.filters.weighting);
+        reports.for_each_valid(config, None, |_, report, duration| timeline.add(report, duration))?;
+        gexf::write_dynamic(&graph, &timeline, path)?;
+        outputs.push(path.clone());
+    }
+
+    let span = appearances.first_start.zip(appearances.last_start);
+    let windows = snapshot::windows(&config.snapshots, span)?;
+    if !windows.is_empty() {
+        create_dir_all(&config.snapshots.output_dir)?;
+    }
+    for window in &windows {
+        let appearances = count_appearances(&reports, config, Some(window))?;
+        println!("Snapshot {}: {} reports", window.label, appearances.reports);
+
+        let mut graph = select(&reports, config, &id_to_name, &appearances, Some(window))?;
+        finish(&mut graph, config);
+
+        let path = config.snapshots.output_dir.join(format!("{}.gexf", window.file_stem()));
+        gexf::write(&graph, &path)?;
+        outputs.push(path.clone());
+        if config.output.graph_json.is_some() {
+            let path = path.with_extension("json");
+            json::write(&graph, &path)?;
+            outputs.push(path);
+        }
+        if config.output.centrality_csv.is_some() {
+            let path = path.with_extension("csv");
+            centrality::write_csv(&graph, &path)?;
+            outputs.push(path);
+        }
+    }
+
+    let manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION"),
+        inputs: vec![
+            FileRecord::hash(&config.input.players)?,
+            FileRecord::new(&config.input.reports, reports.source.hash()),
+        ],
+        config,
+        outputs: outputs.iter().map(|path| FileRecord::hash(path)).collect::<Result<_, _>>()?,
+    };
+    manifest.write(&config.output.manifest)?;
+    println!("Wrote manifest to {}", config.output.manifest.display());
+
+    Ok(())
+}
+
+pub(crate) fn count_appearances(reports: &Reports, config: &Config, window: Option<&Window>) -> Result<Appearances, Box<dyn std::error::Error>> {
+    let mut appearances = Appearances {
+        players: FxHashMap::default(),
+        reports: 0,
+        first_start: None,
+        last_start: None,
+    };
+    let mut too_large = None;
+    reports.for_each_valid(config, window, |_, report, duration| {
+        appearances.reports += 1;
+        appearances.first_start = Some(appearances.first_start.map_or(report.start_time, |t| t.min(report.start_time)));
+        appearances.last_start = Some(appearances.last_start.map_or(report.start_time, |t| t.max(report.start_time)));
+        for &pid in &report.players {
+            let Ok(pid) = u32::try_from(pid) else {
+                too_large = Some(pid);
+                continue;
+            };
+            let stats = appearances.players.entry(pid).or_default();
+            stats.appearances += 1;
+            stats.duration += duration;
+            stats.first_seen = stats.first_seen.min(report.start_time);
+            stats.last_seen = stats.last_seen.max(report.end_time);
+        }
+    })?;
+    if let Some(pid) = too_large {
+        return Err(format!("Player id {} doesn't fit in 32 bits", pid).into());
+    }
+    Ok(appearances)
+}
+
+/// Second pass over the reports: time spent together by every pair of
+/// `included` players, the frequent ones for the atlas, optionally decayed
+/// with each report's age.
+pub(crate) fn accumulate_edges(
+    reports: &Reports,
+    config: &Config,
+    window: Option<&Window>,
+    included: &FxHashSet<u32>,
+    decay: Option<&Decay>,
+) -> Result<FxHashMap<EdgeKey, EdgeStats>, Box<dyn std::error::Error>> {
+    let mut counter = PairCounter::new(config.threads);
+    reports.for_each_valid(config, window, |_, report, duration| {
+        let share = 1.0 / (report.players.len() as f64 - 1.0);
+        let (duration, share) = match decay {
+            Some(decay) => {
+                let factor = decay.factor(report.start_time);
+                ((duration as f64 * factor).round() as u64, share * factor)
+            }
+            None => (duration, share),
+        };
+        let players = report.players.iter().map(|&p| p as u32).filter(|p| included.contains(p));
+        counter.add(players, duration, share);
+    })?;
+    Ok(counter.finish())
+}
+
+/// Weight of the edge `key` under `weighting`, or `None` if it is zero or
+/// less, or not finite, which drops the edge.
+pub(crate) fn edge_weight(weighting: &dyn Weighting, appearances: &Appearances, key: EdgeKey, stats: &EdgeStats) -> Option<f64> {
+    let (a, b) = key.players();
+    let weight = weighting.weight(&PairStats {
+        reports: stats.reports as usize,
+        duration_ms: stats.duration,
+        newman: stats.newman,
+        appearances: (appearances.players[&a].appearances, appearances.players[&b].appearances),
+        total_reports: appearances.reports,
+    });
+    (weight > 0.0 && weight.is_finite()).then_some(weight)
+}
+
+/// Picks frequent players and their heaviest edges out of the reports in
+/// `window`, or all of them.
+fn select(
+    reports: &Reports,
+    config: &Config,
+    id_to_name: &HashMap<usize, String>,
+    appearances: &Appearances,
+    window: Option<&Window>,
+) -> Result<AtlasGraph, Box<dyn std::error::Error>> {
+    let filters = &config.filters;
+    let player_stats = &appearances.players;
+
+    let frequent_players: FxHashSet<u32> = player_stats
+        .iter()
+        .filter(|&(_, stats)| stats.appearances >= filters.min_appearances)
+        .map(|(&pid, _)| pid)
+        .collect();
+    println!("Frequent player count: {}", frequent_players.len());
+
+    let decay = Decay::from_filters(filters, appearances.last_start)?;
+    if let (Some(decay), Some(days)) = (&decay, filters.half_life_days) {
+        println!("Halving report weight every {} days before {}", days, format_date(decay.reference()));
+    }
+    let edge_weights = accumulate_edges(reports, config, window, &frequent_players, decay.as_ref())?;
+    println!("Total Raw Edges: {}", edge_weights.len());
+
+    let weighting = filters.weighting.weighting();
+    let mut weights: FxHashMap<EdgeKey, f64> = FxHashMap::default();
+    for (&key, stats) in &edge_weights {
+        if stats.duration < filters.min_edge_duration_ms {continue;}
+        if let Some(weight) = edge_weight(weighting, appearances, key, stats) {
+            weights.insert(key, weight);
+        }
+    }
+
+    // Hash map order depends on how the maps were filled, so everything
+    // from here on goes by player id to make the output reproducible.
+    let mut candidates: Vec<(EdgeKey, f64)> = weights.into_iter().collect();
+    candidates.sort_unstable_by_key(|&(key, _)| key);
+    let pairs: Vec<(usize, usize, f64)> = candidates
+        .iter()
+        .map(|&(key, w)| {
+            let (a, b) = key.players();
+            (a as usize, b as usize, w)
+        })
+        .collect();
+    let keep = backbone::extract(&pairs, filters);
+
+    let mut ids: Vec<u32> = frequent_players.into_iter().collect();
+    ids.sort_unstable();
+
+    let mut graph = AtlasGraph::default();
+    let mut index_of: FxHashMap<u32, usize> = FxHashMap::default();
+    for id in ids {
+        if let Some(name) = id_to_name.get(&(id as usize)) {
+            let stats = player_stats[&id];
+            index_of.insert(id, graph.nodes.len());
+            graph.nodes.push(AtlasNode {
+                id: id as usize,
+                label: name.clone(),
+                color: DEFAULT_NODE_COLOR.to_string(),
+                appearances: stats.appearances,
+                duration_ms: stats.duration,
+                first_seen: stats.first_seen,
+                last_seen: stats.last_seen,
+                ..AtlasNode::default()
+            });
+        }
+    }
+    for (&(key, weight), _) in candidates.iter().zip(&keep).filter(|&(_, &k)| k) {
+        let (a, b) = key.players();
+        if let (Some(&source), Some(&target)) = (index_of.get(&a), index_of.get(&b)) {
+            let stats = edge_weights[&key];
+            graph.edges.push(AtlasEdge {
+                source,
+                target,
+                weight,
+                duration_ms: stats.duration,
+                reports: stats.reports as usize,
+            });
+        }
+    }
+    graph.size_by_degree();
+    Ok(graph)
+}
+
+/// Community detection, centrality and layout, whichever the config turns on.
+pub(crate) fn finish(graph: &mut AtlasGraph, config: &Config) {
+    if config.community.enabled {
+        community::run(graph, &config.community);
+    }
+
+    if config.centrality.enabled {
+        centrality::run(graph, &config.centrality, config.threads);
+    }
+
+    if config.layout.enabled {
+        println!("Running ForceAtlas2 on {} nodes...", graph.nodes.len());
+        layout::run(graph, &config.layout);
+    }
+}
@@ -0,0 +1,223 @@
+//! Compact binary copy of a report file, so runs after the first skip JSON.
+//!
+//! A cache is named after the BLAKE3 hash of the file it was made from and
+//! repeats the hash in its header, so an edited report file never picks up
+//! a stale cache. Everything is little endian:
+//!
+//! ```text
+//! header:  b"ATLASRPT" | version u32 | source hash [u8; 32]
+//! report:  start u64 | end u64 | player count u32 | players u32… | code length u16 | code
+//! ```
+//!
+//! Reports follow one another until the end of the file.
+
+use std::{
+    fs::{File, remove_file, rename},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+use atlas_model::Report;
+
+const MAGIC: &[u8; 8] = b"ATLASRPT";
+const VERSION: u32 = 1;
+
+pub type Hash = [u8; 32];
+
+pub fn hash_file(path: &Path) -> io::Result<Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(*hasher.finalize().as_bytes())
+}
+
+pub fn hex(hash: &Hash) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Where the cache for a file with this hash lives.
+pub fn path_for(dir: &Path, hash: &Hash) -> PathBuf {
+    dir.join(format!("reports-{}.bin", &hex(hash)[..16]))
+}
+
+/// Writes a cache to a temporary file, which only takes the real name once
+/// every report is in. Dropping the writer unfinished, say on an error,
+/// removes the temporary file.
+pub struct CacheWriter {
+    output: BufWriter<File>,
+    temp: Partial,
+    path: PathBuf,
+}
+
+/// A temporary file that's removed when dropped unless it was kept.
+struct Partial {
+    path: PathBuf,
+    kept: bool,
+}
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        if !self.kept {
+            let _ = remove_file(&self.path);
+        }
+    }
+}
+
+impl CacheWriter {
+    pub fn create(path: &Path, source: &Hash) -> io::Result<Self> {
+        let temp = Partial { path: path.with_extension("partial"), kept: false };
+        let mut output = BufWriter::new(File::create(&temp.path)?);
+        output.write_all(MAGIC)?;
+        output.write_all(&VERSION.to_le_bytes())?;
+        output.write_all(source)?;
+        Ok(CacheWriter { output, temp, path: path.to_path_buf() })
+    }
+
+    pub fn write(&mut self, report: &Report) -> io::Result<()> {
+        let invalid = |what: &str| io::Error::new(ErrorKind::InvalidData, format!("{} in report {}", what, report.code));
+        let code = report.code.as_bytes();
+        let code_len = u16::try_from(code.len()).map_err(|_| invalid("Code too long"))?;
+        let count = u32::try_from(report.players.len()).map_err(|_| invalid("Too many players"))?;
+
+        self.output.write_all(&report.start_time.to_le_bytes())?;
+        self.output.write_all(&report.end_time.to_le_bytes())?;
+        self.output.write_all(&count.to_le_bytes())?;
+        for &player in &report.players {
+            let player = u32::try_from(player).map_err(|_| invalid("Player id over 32 bits"))?;
+            self.output.write_all(&player.to_le_bytes())?;
+        }
+        self.output.write_all(&code_len.to_le_bytes())?;
+        self.output.write_all(code)
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        let CacheWriter { mut output, mut temp, path } = self;
+        output.flush()?;
+        drop(output);
+        rename(&temp.path, &path)?;
+        temp.kept = true;
+        Ok(())
+    }
+
+    /// Drops the cache without giving it the real name.
+    pub fn discard(self) -> io::Result<()> {
+        let CacheWriter { output, mut temp, .. } = self;
+        drop(output);
+        temp.kept = true;
+        remove_file(&temp.path)
+    }
+}
+
+/// Opens the cache at `path` if it exists and was made from a file with
+/// hash `source`.
+pub fn open(path: &Path, source: &Hash) -> io::Result<Option<BufReader<File>>> {
+    let mut input = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut header = [0u8; 8 + 4 + 32];
+    match input.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let current = &header[..8] == MAGIC && header[8..12] == VERSION.to_le_bytes() && &header[12..] == source;
+    Ok(current.then_some(input))
+}
+
+/// Calls `f` with every report in an opened cache and returns how many
+/// there were. The same `Report` is reused for each one.
+pub fn for_each(mut input: impl Read, mut f: impl FnMut(&Report)) -> io::Result<usize> {
+    let mut report = Report { start_time: 0, end_time: 0, players: Vec::new(), code: String::new() };
+    let mut code = Vec::new();
+    let mut count = 0;
+    loop {
+        let mut start = [0u8; 8];
+        match input.read_exact(&mut start) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(count),
+            Err(e) => return Err(e),
+        }
+        report.start_time = u64::from_le_bytes(start);
+        report.end_time = read_u64(&mut input)?;
+
+        let players = read_u32(&mut input)?;
+        report.players.clear();
+        for _ in 0..players {
+            report.players.push(read_u32(&mut input)? as usize);
+        }
+
+        let mut code_len = [0u8; 2];
+        input.read_exact(&mut code_len)?;
+        code.resize(u16::from_le_bytes(code_len) as usize, 0);
+        input.read_exact(&mut code)?;
+        report.code.clear();
+        report.code.push_str(std::str::from_utf8(&code).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?);
+
+        f(&report);
+        count += 1;
+    }
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_dir_all;
+    use crate::testing::{report, temp_dir};
+
+    #[test]
+    fn round_trips_reports() {
+        let dir = temp_dir("cache-round-trip");
+        let source = [7u8; 32];
+        let path = path_for(&dir, &source);
+        let reports = vec![
+            report(1_600_000_000_000, vec![1, 2, 4_000_000_000], "aBcD1234"),
+            report(1_600_000_100_000, vec![], "ünïcödé-日本"),
+            report(1_600_000_200_000, vec![9], ""),
+        ];
+
+        let mut writer = CacheWriter::create(&path, &source).unwrap();
+        for report in &reports {
+            writer.write(report).unwrap();
+        }
+        writer.finish().unwrap();
+        assert!(!path.with_extension("partial").exists());
+
+        let mut read = Vec::new();
+        let input = open(&path, &source).unwrap().expect("cache should match its source");
+        let count = for_each(input, |r| read.push(r.clone())).unwrap();
+        assert_eq!(count, reports.len());
+        assert_eq!(read, reports);
+
+        assert!(open(&path, &[8u8; 32]).unwrap().is_none());
+        assert!(open(&dir.join("missing.bin"), &source).unwrap().is_none());
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unfinished_writer_leaves_nothing_behind() {
+        let dir = temp_dir("cache-unfinished");
+        let source = [1u8; 32];
+        let path = path_for(&dir, &source);
+
+        let mut writer = CacheWriter::create(&path, &source).unwrap();
+        writer.write(&report(0, vec![1, 2], "a")).unwrap();
+        assert!(writer.write(&report(0, vec![1], &"x".repeat(70_000))).is_err());
+        drop(writer);
+
+        assert!(!path.exists());
+        assert!(!path.with_extension("partial").exists());
+        remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+//! Centrality scores per player, for ranking the most connected raiders
+//! on more than one definition. Degree alone mostly reflects the top_k cap.
+//!
+//! Strength, PageRank and eigenvector centrality use edge weights.
+//! Betweenness counts hops instead: weights measure how close two players
+//! are, not how far apart, so there is no natural path length to sum.
+
+use std::{
+    path::Path,
+    sync::{Mutex, atomic::{AtomicUsize, Ordering}},
+    thread,
+};
+use serde::Serialize;
+
+use crate::{config::CentralityConfig, graph::{AtlasGraph, Centrality}, rng::Rng};
+
+const MAX_ITERATIONS: usize = 1000;
+/// Total change in scores across all nodes at which iteration stops.
+const TOLERANCE: f64 = 1e-10;
+/// Betweenness sources are split into this many chunks, summed in order,
+/// so the result doesn't depend on the thread count.
+const CHUNKS: usize = 64;
+
+/// Scores every node of `graph`. `threads` of 0 uses every core for
+/// betweenness.
+pub fn run(graph: &mut AtlasGraph, config: &CentralityConfig, threads: usize) {
+    let n = graph.nodes.len();
+    let mut adjacency: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for edge in &graph.edges {
+        adjacency[edge.source].push((edge.target, edge.weight));
+        adjacency[edge.target].push((edge.source, edge.weight));
+    }
+    let strength: Vec<f64> = adjacency.iter().map(|a| a.iter().map(|&(_, w)| w).sum()).collect();
+
+    let pagerank = pagerank(&adjacency, &strength, config.damping);
+    let eigenvector = eigenvector(&adjacency);
+    let sources = match config.betweenness_samples {
+        0 => (0..n).collect(),
+        k => sample(n, k, config.seed),
+    };
+    println!("Computing betweenness from {} of {} nodes...", sources.len(), n);
+    let betweenness = betweenness(&adjacency, &sources, threads);
+
+    for (i, node) in graph.nodes.iter_mut().enumerate() {
+        node.centrality = Some(Centrality {
+            strength: strength[i],
+            pagerank: pagerank[i],
+            betweenness: betweenness[i],
+            eigenvector: eigenvector[i],
+        });
+    }
+}
+
+/// Weighted PageRank. Players without edges spread their rank evenly.
+fn pagerank(adjacency: &[Vec<(usize, f64)>], strength: &[f64], damping: f64) -> Vec<f64> {
+    let n = adjacency.len();
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..MAX_ITERATIONS {
+        let dangling: f64 = (0..n).filter(|&i| strength[i] == 0.0).map(|i| rank[i]).sum();
+        let mut next = vec![(1.0 - damping + damping * dangling) / n as f64; n];
+        for (i, neighbours) in adjacency.iter().enumerate() {
+            if strength[i] == 0.0 {
+                continue;
+            }
+            let share = damping * rank[i] / strength[i];
+            for &(j, w) in neighbours {
+                next[j] += share * w;
+            }
+        }
+        let change: f64 = rank.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+    rank
+}
+
+/// Weighted eigenvector centrality by power iteration, scaled to unit length.
+/// Iterating on A + I rather than A keeps it from oscillating on
+/// bipartite parts of the graph without changing the eigenvector.
+fn eigenvector(adjacency: &[Vec<(usize, f64)>]) -> Vec<f64> {
+    let n = adjacency.len();
+    let mut x = vec![1.0 / (n as f64).sqrt(); n];
+    for _ in 0..MAX_ITERATIONS {
+        let mut next = x.clone();
+        for (i, neighbours) in adjacency.iter().enumerate() {
+            for &(j, w) in neighbours {
+                next[j] += w * x[i];
+            }
+        }
+        let norm = next.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm == 0.0 {
+            return next;
+        }
+        next.iter_mut().for_each(|v| *v /= norm);
+        let change: f64 = x.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        x = next;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+    x
+}
+
+/// `k` distinct nodes out of `n`, in index order.
+fn sample(n: usize, k: usize, seed: u64) -> Vec<usize> {
+    let mut nodes: Vec<usize> = (0..n).collect();
+    let k = k.min(n);
+    let mut rng = Rng::new(seed);
+    for i in 0..k {
+        let j = i + (rng.next_u64() % (n - i) as u64) as usize;
+        nodes.swap(i, j);
+    }
+    nodes.truncate(k);
+    nodes.sort_unstable();
+    nodes
+}
+
+/// Brandes' betweenness over shortest paths from `sources`, normalised to
+/// `[0, 1]` and scaled up to estimate the exact value when only some nodes
+/// are sources.
+fn betweenness(adjacency: &[Vec<(usize, f64)>], sources: &[usize], threads: usize) -> Vec<f64> {
+    let n = adjacency.len();
+    if n < 3 || sources.is_empty() {
+        return vec![0.0; n];
+    }
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+
+    let chunk_size = sources.len().div_ceil(CHUNKS);
+    let chunks: Vec<&[usize]> = sources.chunks(chunk_size).collect();
+    let partials: Mutex<Vec<Option<Vec<f64>>>> = Mutex::new(vec![None; chunks.len()]);
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..threads.min(chunks.len()) {
+            scope.spawn(|| {
+                let mut search = Search::new(n);
+                loop {
+                    let c = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(chunk) = chunks.get(c) else { break };
+                    let mut partial = vec![0.0; n];
+                    for &s in *chunk {
+                        search.accumulate(adjacency, s, &mut partial);
+                    }
+                    partials.lock().unwrap()[c] = Some(partial);
+                }
+            });
+        }
+    });
+
+    let mut total = vec![0.0; n];
+    for partial in partials.into_inner().unwrap().into_iter().flatten() {
+        total.iter_mut().zip(partial).for_each(|(t, p)| *t += p);
+    }
+    // Every path is found from both of its ends, which the usual
+    // (n - 1)(n - 2) normalisation for undirected graphs already allows for.
+    let scale = n as f64 / sources.len() as f64 / ((n - 1) * (n - 2)) as f64;
+    total.iter_mut().for_each(|t| *t *= scale);
+    total
+}
+
+/// Buffers for one breadth first search, reused between sources.
+struct Search {
+    distance: Vec<usize>,
+    paths: Vec<f64>,
+    dependency: Vec<f64>,
+    order: Vec<usize>,
+}
+
+impl Search {
+    fn new(n: usize) -> Self {
+        Search { distance: vec![usize::MAX; n], paths: vec![0.0; n], dependency: vec![0.0; n], order: Vec::with_capacity(n) }
+    }
+
+    /// Adds each node's dependency on paths from `source` to `total`.
+    fn accumulate(&mut self, adjacency: &[Vec<(usize, f64)>], source: usize, total: &mut [f64]) {
+        for &v in &self.order {
+            self.distance[v] = usize::MAX;
+            self.paths[v] = 0.0;
+            self.dependency[v] = 0.0;
+        }
+        self.order.clear();
+
+        self.distance[source] = 0;
+        self.paths[source] = 1.0;
+        self.order.push(source);
+        let mut head = 0;
+        while head < self.order.len() {
+            let v = self.order[head];
+            head += 1;
+            for &(w, _) in &adjacency[v] {
+                if self.distance[w] == usize::MAX {
+                    self.distance[w] = self.distance[v] + 1;
+                    self.order.push(w);
+                }
+                if self.distance[w] == self.distance[v] + 1 {
+                    self.paths[w] += self.paths[v];
+                }
+            }
+        }
+
+        // Predecessors are the neighbours one step closer to the source.
+        for &w in self.order.iter().rev() {
+            let coefficient = (1.0 + self.dependency[w]) / self.paths[w];
+            for &(v, _) in &adjacency[w] {
+                if self.distance[v] != usize::MAX && self.distance[v] + 1 == self.distance[w] {
+                    self.dependency[v] += self.paths[v] * coefficient;
+                }
+            }
+            if w != source {
+                total[w] += self.dependency[w];
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    id: usize,
+    name: &'a str,
+    degree: usize,
+    strength: f64,
+    pagerank: f64,
+    betweenness: f64,
+    eigenvector: f64,
+}
+
+/// Writes every scored node's centralities as CSV, in node order.
+pub fn write_csv(graph: &AtlasGraph, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let degrees = graph.degrees();
+    let mut wtr = csv::Writer::from_path(path)?;
+    for (node, &degree) in graph.nodes.iter().zip(&degrees) {
+        let Some(c) = node.centrality else { continue };
+        wtr.serialize(Row {
+            id: node.id,
+            name: &node.label,
+            degree,
+            strength: c.strength,
+            pagerank: c.pagerank,
+            betweenness: c.betweenness,
+            eigenvector: c.eigenvector,
+        })?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{AtlasEdge, AtlasNode};
+
+    fn graph(n: usize, edges: &[(usize, usize)]) -> AtlasGraph {
+        AtlasGraph {
+            nodes: (0..n).map(|id| AtlasNode { id, ..AtlasNode::default() }).collect(),
+            edges: edges
+                .iter()
+                .map(|&(source, target)| AtlasEdge { source, target, weight: 1.0 + source as f64, ..AtlasEdge::default() })
+                .collect(),
+            laid_out: false,
+        }
+    }
+
+    fn scores(graph: &AtlasGraph) -> Vec<Centrality> {
+        graph.nodes.iter().map(|n| n.centrality.unwrap()).collect()
+    }
+
+    #[test]
+    fn pagerank_sums_to_one() {
+        // Node 5 has no edges and spreads its rank evenly.
+        let mut graph = graph(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4)]);
+        run(&mut graph, &CentralityConfig::default(), 1);
+        let total: f64 = scores(&graph).iter().map(|c| c.pagerank).sum();
+        assert!((total - 1.0).abs() < 1e-9, "{}", total);
+    }
+
+    #[test]
+    fn betweenness_of_a_star_and_a_path() {
+        let mut star = graph(5, &[(0, 1), (0, 2), (0, 3), (0, 4)]);
+        run(&mut star, &CentralityConfig::default(), 1);
+        let betweenness: Vec<f64> = scores(&star).iter().map(|c| c.betweenness).collect();
+        assert_eq!(betweenness, [1.0, 0.0, 0.0, 0.0, 0.0]);
+
+        // The middle of 0-1-2-3-4 is on 4 of the 6 paths between the others,
+        // its neighbours on 3.
+        let mut path = graph(5, &[(0, 1), (1, 2), (2, 3), (3, 4)]);
+        run(&mut path, &CentralityConfig::default(), 2);
+        let expected = [0.0, 0.5, 2.0 / 3.0, 0.5, 0.0];
+        for (c, expected) in scores(&path).iter().zip(expected) {
+            assert!((c.betweenness - expected).abs() < 1e-12, "{} not {}", c.betweenness, expected);
+        }
+    }
+
+    #[test]
+    fn sampling_every_node_is_exact() {
+        let edges = [(0, 1), (1, 2), (2, 3), (3, 0), (3, 4), (4, 5), (5, 6), (6, 4), (1, 7)];
+        let mut exact = graph(8, &edges);
+        run(&mut exact, &CentralityConfig::default(), 1);
+        for samples in [8, 20] {
+            let mut sampled = graph(8, &edges);
+            let config = CentralityConfig { betweenness_samples: samples, seed: 3, ..CentralityConfig::default() };
+            run(&mut sampled, &config, 3);
+            let (a, b) = (scores(&exact), scores(&sampled));
+            assert!(a.iter().zip(&b).all(|(a, b)| a.betweenness == b.betweenness));
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+
+use processing::{activity::ActivityOptions, backbone::Method, config::{Config, WindowConfig}, dates::Period, ego::EgoOptions, statics::StaticOptions, weighting::Scheme};
+
+#[derive(Debug, Parser)]
+#[command(about = "Turns collected esologs reports into a raider graph")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Build the graph from a player table and report file
+    Build(Box<BuildArgs>),
+    /// Combine collection runs into merged_players.csv and merged_reports.json
+    Merge(MergeArgs),
+    /// Check a player table and report file for bad data
+    Validate(ValidateArgs),
+    /// Show how two players are connected in a built graph
+    Path(PathArgs),
+    /// List a player's partners and write their neighbourhood as a graph
+    Ego(EgoArgs),
+    /// Find groups of players who keep raiding together
+    Statics(StaticsArgs),
+    /// Export each player's reports, hours and partners per month
+    Activity(ActivityArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct MergeArgs {
+    /// Directories holding a player_table.csv and report_details.json each
+    #[arg(required = true)]
+    pub datasets: Vec<PathBuf>,
+    /// Where to write the merged files, created if missing
+    #[arg(long, default_value = ".")]
+    pub output_dir: PathBuf,
+    /// Skip malformed report lines instead of failing, logging them to --rejects
+    #[arg(long)]
+    pub lenient: bool,
+    /// Where --lenient logs the lines it skips
+    #[arg(long, default_value = "rejects.txt")]
+    pub rejects: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ValidateArgs {
+    /// Player table csv
+    #[arg(long, default_value = "merged_players.csv")]
+    pub players: PathBuf,
+    /// Report details, one json object per line
+    #[arg(long, default_value = "merged_reports.json")]
+    pub reports: PathBuf,
+    /// Reports longer than this many hours are flagged
+    #[arg(long, default_value_t = 24)]
+    pub max_duration_hours: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct PathArgs {
+    /// Display name to start from, e.g. @someone
+    pub from: String,
+    /// Display name to end at
+    pub to: String,
+    /// Graph json written by build
+    #[arg(long, default_value = "../visualisation/static/graph")]
+    pub graph: PathBuf,
+}
+
+/// Inputs shared by every command that reads reports.
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// TOML profile to start from; flags override it
+    #[arg(long)]
+    pub profile: Option<PathBuf>,
+    /// Player table csv
+    #[arg(long)]
+    pub players: Option<PathBuf>,
+    /// Report details, one json object per line
+    #[arg(long)]
+    pub reports: Option<PathBuf>,
+    /// Always parse the report JSON, without reading or writing a cache
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Skip malformed report lines instead of failing, logging them
+    #[arg(long)]
+    pub lenient: bool,
+    /// Where lenient reading logs the lines it skips
+    #[arg(long)]
+    pub rejects: Option<PathBuf>,
+    /// Count reports of the same raid uploaded by several players once
+    #[arg(long)]
+    pub dedup: bool,
+    /// Share of players two overlapping reports need in common to be one raid
+    #[arg(long)]
+    pub min_overlap: Option<f64>,
+}
+
+impl InputArgs {
+    /// The profile, or the defaults, with the input and dedup flags applied.
+    pub fn config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = match &self.profile {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        if let Some(players) = &self.players {
+            config.input.players = players.clone();
+        }
+        if let Some(reports) = &self.reports {
+            config.input.reports = reports.clone();
+        }
+        if self.no_cache {
+            config.input.cache = false;
+        }
+        if self.lenient {
+            config.input.lenient = true;
+        }
+        if let Some(rejects) = &self.rejects {
+            config.input.rejects = rejects.clone();
+        }
+        if self.dedup {
+            config.dedup.enabled = true;
+        }
+        if let Some(overlap) = self.min_overlap {
+            config.dedup.min_overlap = overlap;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct EgoArgs {
+    /// Display name of the player, e.g. @someone
+    pub name: String,
+    /// 1 for the player's partners, 2 for their partners' partners too
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub hops: u8,
+    /// Reports two players must share to count as partners
+    #[arg(long, default_value_t = 1)]
+    pub min_reports: usize,
+    /// How many partners to list
+    #[arg(long, default_value_t = 25)]
+    pub top: usize,
+    #[command(flatten)]
+    pub input: InputArgs,
+    /// Where to write the ego graph's gexf
+    #[arg(long, default_value = "ego.gexf")]
+    pub output: PathBuf,
+    /// Also write the ego graph as json here
+    #[arg(long)]
+    pub graph_json: Option<PathBuf>,
+    /// Skip the ForceAtlas2 layout
+    #[arg(long)]
+    pub no_layout: bool,
+}
+
+impl EgoArgs {
+    pub fn config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = self.input.config()?;
+        if self.no_layout {
+            config.layout.enabled = false;
+        }
+        Ok(config)
+    }
+
+    pub fn options(&self) -> EgoOptions<'_> {
+        EgoOptions {
+            name: &self.name,
+            hops: self.hops as usize,
+            min_reports: self.min_reports,
+            top: self.top,
+            gexf: &self.output,
+            graph_json: self.graph_json.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct StaticsArgs {
+    /// Smallest group to report
+    #[arg(long, default_value_t = 8)]
+    pub min_size: usize,
+    /// Runs a group needs, and reports every pair in it must share
+    #[arg(long, default_value_t = 10)]
+    pub min_runs: usize,
+    /// Part of a group that must be in a report for it to count as a run
+    #[arg(long, default_value_t = 0.75)]
+    pub min_share: f64,
+    /// How many groups to list and write
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+    #[command(flatten)]
+    pub input: InputArgs,
+    /// Where to write the groups as json
+    #[arg(long, default_value = "statics.json")]
+    pub output: PathBuf,
+}
+
+impl StaticsArgs {
+    pub fn options(&self) -> StaticOptions<'_> {
+        StaticOptions {
+            min_size: self.min_size,
+            min_runs: self.min_runs,
+            min_share: self.min_share,
+            top: self.top,
+            output: &self.output,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ActivityArgs {
+    /// Display name to follow; repeat for more. Without any, every player
+    /// with enough appearances is followed
+    #[arg(long = "player")]
+    pub names: Vec<String>,
+    /// Month, quarter or year
+    #[arg(long, default_value = "month")]
+    pub period: Period,
+    /// Valid reports a player needs to be followed; defaults to the profile's
+    #[arg(long)]
+    pub min_appearances: Option<usize>,
+    #[command(flatten)]
+    pub input: InputArgs,
+    /// Where to write one row per player and period
+    #[arg(long, default_value = "activity.csv")]
+    pub output: PathBuf,
+    /// Also write each player's timeline as json here
+    #[arg(long)]
+    pub json: Option<PathBuf>,
+}
+
+impl ActivityArgs {
+    pub fn config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = self.input.config()?;
+        if let Some(min) = self.min_appearances {
+            config.filters.min_appearances = min;
+        }
+        Ok(config)
+    }
+
+    pub fn options(&self) -> ActivityOptions<'_> {
+        ActivityOptions {
+            period: self.period,
+            names: &self.names,
+            csv: &self.output,
+            json: self.json.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BuildArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Where to write the gexf file
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Also write the visualisation's graph json here
+    #[arg(long)]
+    pub graph_json: Option<PathBuf>,
+    /// Also write a dynamic gexf with per-period activity here
+    #[arg(long)]
+    pub dynamic_gexf: Option<PathBuf>,
+    /// Period for the dynamic gexf: month, quarter or year
+    #[arg(long)]
+    pub dynamic_period: Option<Period>,
+
+    /// Drop reports with this many players or more
+    #[arg(long)]
+    pub max_report_players: Option<usize>,
+    /// Drop players with fewer appearances than this
+    #[arg(long)]
+    pub min_appearances: Option<usize>,
+    /// Cap on how much a single report adds to an edge, in milliseconds
+    #[arg(long)]
+    pub max_report_duration_ms: Option<u64>,
+    /// Drop edges lighter than this, in milliseconds
+    #[arg(long)]
+    pub min_edge_duration_ms: Option<u64>,
+    /// Edge backbone: top_k, disparity, noise_corrected or quantile
+    #[arg(long)]
+    pub backbone: Option<Method>,
+    /// Number of heaviest edges kept per player
+    #[arg(long)]
+    pub top_k: Option<usize>,
+    /// Significance level for the disparity filter
+    #[arg(long)]
+    pub alpha: Option<f64>,
+    /// Standard deviations for the noise-corrected backbone
+    #[arg(long)]
+    pub noise_delta: Option<f64>,
+    /// Weight quantile an edge must reach for the quantile backbone
+    #[arg(long)]
+    pub quantile: Option<f64>,
+    /// Edge weighting: count, duration, newman, jaccard, overlap or pmi
+    #[arg(long)]
+    pub weighting: Option<Scheme>,
+    /// Halve a report's weight every this many days before the reference date
+    #[arg(long)]
+    pub half_life_days: Option<f64>,
+    /// YYYY-MM-DD to measure report ages back from; defaults to the latest report
+    #[arg(long)]
+    pub reference_date: Option<String>,
+
+    /// Skip the ForceAtlas2 layout
+    #[arg(long)]
+    pub no_layout: bool,
+    /// Number of ForceAtlas2 iterations
+    #[arg(long)]
+    pub layout_iterations: Option<usize>,
+    /// Compare every pair of nodes instead of using Barnes–Hut
+    #[arg(long)]
+    pub exact_repulsion: bool,
+    /// Barnes–Hut accuracy; higher is faster and rougher
+    #[arg(long)]
+    pub theta: Option<f64>,
+    /// Skip community detection
+    #[arg(long)]
+    pub no_communities: bool,
+    /// Modularity resolution, on Gephi's scale
+    #[arg(long)]
+    pub resolution: Option<f64>,
+    /// Skip centrality scores
+    #[arg(long)]
+    pub no_centrality: bool,
+    /// Estimate betweenness from this many random players (0 is exact)
+    #[arg(long)]
+    pub betweenness_samples: Option<usize>,
+    /// Also write every player's centrality scores as csv here
+    #[arg(long)]
+    pub centrality_csv: Option<PathBuf>,
+
+    /// Also build one graph per month, quarter or year
+    #[arg(long)]
+    pub snapshot_period: Option<Period>,
+    /// Also build a graph for a date range, as NAME:START:END with
+    /// YYYY-MM-DD dates and END left out; may be repeated
+    #[arg(long, value_parser = parse_window)]
+    pub snapshot_window: Vec<WindowConfig>,
+    /// Directory for the snapshot graphs
+    #[arg(long)]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// Threads used to count pairs; 0 uses every core
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Seed for the layout, community detection and betweenness sampling
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl BuildArgs {
+    pub fn config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = self.input.config()?;
+        if let Some(output) = &self.output {
+            config.output.gexf = output.clone();
+        }
+        if let Some(graph_json) = &self.graph_json {
+            config.output.graph_json = Some(graph_json.clone());
+        }
+        if let Some(dynamic_gexf) = &self.dynamic_gexf {
+            config.output.dynamic_gexf = Some(dynamic_gexf.clone());
+        }
+        if let Some(period) = self.dynamic_period {
+            config.output.dynamic_period = period;
+        }
+
+        let filters = &mut config.filters;
+        if let Some(n) = self.max_report_players {
+            filters.max_report_players = n;
+        }
+        if let Some(n) = self.min_appearances {
+            filters.min_appearances = n;
+        }
+        if let Some(ms) = self.max_report_duration_ms {
+            filters.max_report_duration_ms = ms;
+        }
+        if let Some(ms) = self.min_edge_duration_ms {
+            filters.min_edge_duration_ms = ms;
+        }
+        if let Some(method) = self.backbone {
+            filters.backbone = method;
+        }
+        if let Some(k) = self.top_k {
+            filters.top_k = k;
+        }
+        if let Some(alpha) = self.alpha {
+            filters.alpha = alpha;
+        }
+        if let Some(delta) = self.noise_delta {
+            filters.noise_delta = delta;
+        }
+        if let Some(q) = self.quantile {
+            filters.quantile = q;
+        }
+        if let Some(scheme) = self.weighting {
+            filters.weighting = scheme;
+        }
+        if let Some(days) = self.half_life_days {
+            filters.half_life_days = Some(days);
+        }
+        if let Some(date) = &self.reference_date {
+            filters.reference_date = Some(date.clone());
+        }
+
+        if self.no_layout {
+            config.layout.enabled = false;
+        }
+        if let Some(n) = self.layout_iterations {
+            config.layout.iterations = n;
+        }
+        if self.exact_repulsion {
+            config.layout.barnes_hut = false;
+        }
+        if let Some(theta) = self.theta {
+            config.layout.theta = theta;
+        }
+        if self.no_communities {
+            config.community.enabled = false;
+        }
+        if let Some(resolution) = self.resolution {
+            config.community.resolution = resolution;
+        }
+        if self.no_centrality {
+            config.centrality.enabled = false;
+        }
+        if let Some(samples) = self.betweenness_samples {
+            config.centrality.betweenness_samples = samples;
+        }
+        if let Some(path) = &self.centrality_csv {
+            config.output.centrality_csv = Some(path.clone());
+        }
+        if let Some(period) = self.snapshot_period {
+            config.snapshots.period = Some(period);
+        }
+        config.snapshots.windows.extend(self.snapshot_window.iter().cloned());
+        if let Some(dir) = &self.snapshot_dir {
+            config.snapshots.output_dir = dir.clone();
+        }
+        if let Some(threads) = self.threads {
+            config.threads = threads;
+        }
+        if let Some(seed) = self.seed {
+            config.layout.seed = seed;
+            config.community.seed = seed;
+            config.centrality.seed = seed;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn parse_window(text: &str) -> Result<WindowConfig, String> {
+    match text.splitn(3, ':').collect::<Vec<_>>()[..] {
+        [name, start, end] => Ok(WindowConfig {
+            name: name.to_string(),
+            start: start.to_string(),
+            end: end.to_string(),
+        }),
+        _ => Err(format!("Expected NAME:START:END, got {:?}", text)),
+    }
+}
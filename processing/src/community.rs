@@ -0,0 +1,277 @@
+//! Louvain modularity communities (Blondel et al. 2008).
+//!
+//! Resolution follows Gephi's convention rather than the usual γ: the
+//! internal weight term is scaled by it, so values below 1 give more, smaller
+//! communities. The README's 0.2 therefore means the same thing here.
+
+use std::collections::HashMap;
+
+use crate::{config::CommunityConfig, graph::AtlasGraph, rng::Rng};
+
+/// Colours handed out to communities from largest to smallest, wrapping
+/// around once they run out.
+pub const PALETTE: [&str; 20] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+    "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf",
+    "#aec7e8", "#ffbb78", "#98df8a", "#ff9896", "#c5b0d5",
+    "#c49c94", "#f7b6d2", "#c7c7c7", "#dbdb8d", "#9edae5",
+];
+
+/// Weighted graph for one level of the algorithm. Self loops hold the weight
+/// inside a node that was formed by merging a community, counted twice like
+/// every other entry of the adjacency matrix.
+struct Level {
+    adjacency: Vec<Vec<(usize, f64)>>,
+    degree: Vec<f64>,
+    total: f64,
+}
+
+impl Level {
+    fn from_graph(graph: &AtlasGraph) -> Self {
+        let n = graph.nodes.len();
+        let mut adjacency = vec![Vec::new(); n];
+        for edge in &graph.edges {
+            adjacency[edge.source].push((edge.target, edge.weight));
+            adjacency[edge.target].push((edge.source, edge.weight));
+        }
+        Level::new(adjacency)
+    }
+
+    fn new(adjacency: Vec<Vec<(usize, f64)>>) -> Self {
+        let degree: Vec<f64> = adjacency.iter().map(|n| n.iter().map(|&(_, w)| w).sum()).collect();
+        let total = degree.iter().sum();
+        Level { adjacency, degree, total }
+    }
+
+    /// Moves single nodes between communities until none of them wants to
+    /// move. Returns the community of each node and whether anything moved.
+    fn local_moves(&self, resolution: f64, rng: &mut Rng) -> (Vec<usize>, bool) {
+        let n = self.adjacency.len();
+        let mut community: Vec<usize> = (0..n).collect();
+        let mut community_total = self.degree.clone();
+        let mut order: Vec<usize> = (0..n).collect();
+        shuffle(&mut order, rng);
+
+        let mut any_moved = false;
+        let mut links: HashMap<usize, f64> = HashMap::new();
+        loop {
+            let mut moved = false;
+            for &node in &order {
+                links.clear();
+                for &(neighbour, weight) in &self.adjacency[node] {
+                    if neighbour != node {
+                        *links.entry(community[neighbour]).or_insert(0.0) += weight;
+                    }
+                }
+
+                let current = community[node];
+                let k = self.degree[node];
+                community_total[current] -= k;
+
+                let gain = |c: usize, links_to: f64| resolution * links_to - k * community_total[c] / self.total;
+                let mut best = current;
+                let mut best_gain = gain(current, links.get(&current).copied().unwrap_or(0.0));
+                let mut candidates: Vec<(usize, f64)> = links.iter().map(|(&c, &w)| (c, w)).collect();
+                candidates.sort_unstable_by_key(|&(c, _)| c);
+                for (c, links_to) in candidates {
+                    let g = gain(c, links_to);
+                    if g > best_gain + 1e-12 {
+                        best = c;
+                        best_gain = g;
+                    }
+                }
+
+                community_total[best] += k;
+                if best != current {
+                    community[node] = best;
+                    moved = true;
+                    any_moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+
+        (renumber(&community), any_moved)
+    }
+
+    /// Collapses every community into a single node.
+    fn aggregate(&self, community: &[usize], count: usize) -> Level {
+        let mut merged: Vec<HashMap<usize, f64>> = vec![HashMap::new(); count];
+        for (node, neighbours) in self.adjacency.iter().enumerate() {
+            for &(neighbour, weight) in neighbours {
+                *merged[community[node]].entry(community[neighbour]).or_insert(0.0) += weight;
+            }
+        }
+        let adjacency = merged
+            .into_iter()
+            .map(|m| {
+                let mut v: Vec<(usize, f64)> = m.into_iter().collect();
+                v.sort_unstable_by_key(|&(c, _)| c);
+                v
+            })
+            .collect();
+        Level::new(adjacency)
+    }
+}
+
+fn shuffle(items: &mut [usize], rng: &mut Rng) {
+    for i in (1..items.len()).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// Maps community labels onto `0..count` in order of first appearance.
+fn renumber(community: &[usize]) -> Vec<usize> {
+    let mut ids = HashMap::new();
+    community
+        .iter()
+        .map(|c| {
+            let next = ids.len();
+            *ids.entry(*c).or_insert(next)
+        })
+        .collect()
+}
+
+/// Modularity of a partition under the same resolution convention.
+pub fn modularity(graph: &AtlasGraph, community: &[usize], resolution: f64) -> f64 {
+    let level = Level::from_graph(graph);
+    if level.total == 0.0 {
+        return 0.0;
+    }
+    let count = community.iter().copied().max().map_or(0, |m| m + 1);
+    let mut inside = vec![0.0; count];
+    let mut total = vec![0.0; count];
+    for (node, neighbours) in level.adjacency.iter().enumerate() {
+        total[community[node]] += level.degree[node];
+        for &(neighbour, weight) in neighbours {
+            if community[neighbour] == community[node] {
+                inside[community[node]] += weight;
+            }
+        }
+    }
+    inside
+        .iter()
+        .zip(&total)
+        .map(|(&i, &t)| resolution * i / level.total - (t / level.total).powi(2))
+        .sum()
+}
+
+/// Finds communities and returns one id per node, with 0 the largest
+/// community, 1 the next largest and so on.
+pub fn detect(graph: &AtlasGraph, config: &CommunityConfig) -> Vec<usize> {
+    let mut rng = Rng::new(config.seed);
+    let mut membership: Vec<usize> = (0..graph.nodes.len()).collect();
+    let mut level = Level::from_graph(graph);
+
+    loop {
+        // Without any weight every gain is 0/0, so leave each node alone.
+        if level.total == 0.0 {
+            break;
+        }
+        let (community, moved) = level.local_moves(config.resolution, &mut rng);
+        if !moved {
+            break;
+        }
+        for c in membership.iter_mut() {
+            *c = community[*c];
+        }
+        let count = community.iter().copied().max().map_or(0, |m| m + 1);
+        if count == level.adjacency.len() {
+            break;
+        }
+        level = level.aggregate(&community, count);
+    }
+
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for &c in &membership {
+        *sizes.entry(c).or_insert(0) += 1;
+    }
+    let mut by_size: Vec<(usize, usize)> = sizes.into_iter().collect();
+    by_size.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let rank: HashMap<usize, usize> = by_size.iter().enumerate().map(|(r, &(c, _))| (c, r)).collect();
+    membership.iter().map(|c| rank[c]).collect()
+}
+
+/// Runs [`detect`] and stores the community and its colour on each node.
+pub fn run(graph: &mut AtlasGraph, config: &CommunityConfig) {
+    let membership = detect(graph, config);
+    let count = membership.iter().copied().max().map_or(0, |m| m + 1);
+    println!(
+        "Found {} communities, modularity {:.4} at resolution 1",
+        count,
+        modularity(graph, &membership, 1.0)
+    );
+    for (node, community) in graph.nodes.iter_mut().zip(membership) {
+        node.community = Some(community);
+        node.color = PALETTE[community % PALETTE.len()].to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{AtlasEdge, AtlasNode};
+
+    /// Disjoint cliques of the given sizes, plus `extra` edges between them.
+    fn graph(sizes: &[usize], extra: &[(usize, usize)]) -> AtlasGraph {
+        let mut edges = Vec::new();
+        let mut first = 0;
+        for &size in sizes {
+            for a in first..first + size {
+                for b in a + 1..first + size {
+                    edges.push((a, b));
+                }
+            }
+            first += size;
+        }
+        edges.extend_from_slice(extra);
+        AtlasGraph {
+            nodes: (0..first).map(|id| AtlasNode { id, ..AtlasNode::default() }).collect(),
+            edges: edges
+                .into_iter()
+                .map(|(source, target)| AtlasEdge { source, target, weight: 1.0, ..AtlasEdge::default() })
+                .collect(),
+            laid_out: false,
+        }
+    }
+
+    fn config(resolution: f64, seed: u64) -> CommunityConfig {
+        CommunityConfig { resolution, seed, ..CommunityConfig::default() }
+    }
+
+    #[test]
+    fn splits_bridged_cliques_largest_first() {
+        let graph = graph(&[4, 6], &[(3, 4)]);
+        let membership = detect(&graph, &config(1.0, 7));
+        assert_eq!(membership, [1, 1, 1, 1, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn same_seed_same_membership() {
+        let graph = graph(&[5, 5, 5, 3], &[(0, 5), (6, 10), (11, 15), (1, 16)]);
+        for seed in 0..5 {
+            assert_eq!(detect(&graph, &config(1.0, seed)), detect(&graph, &config(1.0, seed)));
+        }
+    }
+
+    #[test]
+    fn modularity_of_bridged_triangles() {
+        // m = 7, each side has 3 internal edges and a degree sum of 7:
+        // 2 * (3/7 - (7/14)^2) = 5/14.
+        let graph = graph(&[3, 3], &[(2, 3)]);
+        let q = modularity(&graph, &[0, 0, 0, 1, 1, 1], 1.0);
+        assert!((q - 5.0 / 14.0).abs() < 1e-12, "{q}");
+        assert_eq!(modularity(&graph, &[0; 6], 1.0), 0.0);
+    }
+
+    #[test]
+    fn edgeless_graph_keeps_singletons() {
+        let graph = graph(&[1, 1, 1], &[]);
+        let mut membership = detect(&graph, &config(0.2, 0));
+        membership.sort_unstable();
+        assert_eq!(membership, [0, 1, 2]);
+    }
+}
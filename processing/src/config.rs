@@ -0,0 +1,353 @@
+use std::{fs::read_to_string, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+use crate::{backbone::Method, dates::Period, weighting::Scheme};
+
+/// Everything a `build` run needs. Loaded from a TOML profile, then
+/// overridden by command line flags. Any key left out of a profile keeps the
+/// value used for the published atlas.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub input: InputConfig,
+    pub output: OutputConfig,
+    pub filters: FilterConfig,
+    pub layout: LayoutConfig,
+    pub community: CommunityConfig,
+    pub centrality: CentralityConfig,
+    pub snapshots: SnapshotConfig,
+    pub dedup: DedupConfig,
+    /// Threads used to count pairs. 0 uses every core.
+    pub threads: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub players: PathBuf,
+    pub reports: PathBuf,
+    /// Keep a binary copy of the reports in `cache_dir` and read that
+    /// instead of the JSON while the report file is unchanged.
+    pub cache: bool,
+    pub cache_dir: PathBuf,
+    /// Skip malformed report lines, logging them to `rejects`, rather than
+    /// failing the run.
+    pub lenient: bool,
+    pub rejects: PathBuf,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            players: PathBuf::from("merged_players.csv"),
+            reports: PathBuf::from("merged_reports.json"),
+            cache: true,
+            cache_dir: PathBuf::from("cache"),
+            lenient: false,
+            rejects: PathBuf::from("rejects.txt"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub gexf: PathBuf,
+    /// The visualisation's `static/graph`, if it should be written too.
+    pub graph_json: Option<PathBuf>,
+    /// A dynamic gexf of the same graph for Gephi's timeline, if wanted.
+    pub dynamic_gexf: Option<PathBuf>,
+    /// How finely the dynamic gexf slices time.
+    pub dynamic_period: Period,
+    /// Hashes of the inputs and outputs and the full config of the run.
+    pub manifest: PathBuf,
+    /// Every player's centrality scores as CSV, if wanted.
+    pub centrality_csv: Option<PathBuf>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            gexf: PathBuf::from("output.gexf"),
+            graph_json: None,
+            dynamic_gexf: None,
+            dynamic_period: Period::Month,
+            manifest: PathBuf::from("manifest.json"),
+            centrality_csv: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    /// Reports with this many players or more are dropped.
+    pub max_report_players: usize,
+    /// Players seen in fewer valid reports than this are dropped.
+    pub min_appearances: usize,
+    /// Each report adds at most this much to an edge.
+    pub max_report_duration_ms: u64,
+    /// Edges with less total time than this are dropped.
+    pub min_edge_duration_ms: u64,
+    /// How the edge list is thinned out.
+    pub backbone: Method,
+    /// How many of their heaviest edges each player keeps, for `top_k`.
+    pub top_k: usize,
+    /// Significance level for `disparity`.
+    pub alpha: f64,
+    /// Standard deviations above expectation for `noise_corrected`.
+    pub noise_delta: f64,
+    /// Weight quantile edges must reach for `quantile`.
+    pub quantile: f64,
+    /// How edges are weighed, which also decides which are the heaviest.
+    pub weighting: Scheme,
+    /// Halve a report's contribution to edge time every this many days it
+    /// is older than `reference_date`. Unset counts every report fully.
+    /// Only the `duration` and `newman` weightings allow it.
+    pub half_life_days: Option<f64>,
+    /// `YYYY-MM-DD` that report ages are measured back from. Defaults to
+    /// the start of the latest report.
+    pub reference_date: Option<String>,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        FilterConfig {
+            max_report_players: 30,
+            min_appearances: 200,
+            max_report_duration_ms: 7_200_000, // 2 hours
+            min_edge_duration_ms: 86_400_000, // 1 day
+            backbone: Method::TopK,
+            top_k: 30,
+            alpha: 0.05,
+            noise_delta: 1.64,
+            quantile: 0.9,
+            weighting: Scheme::Duration,
+            half_life_days: None,
+            reference_date: None,
+        }
+    }
+}
+
+/// ForceAtlas2 settings. The defaults are the ones the README lists for the
+/// Gephi run behind the published atlas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub enabled: bool,
+    pub iterations: usize,
+    pub gravity: f64,
+    pub scaling_ratio: f64,
+    pub lin_log: bool,
+    pub prevent_overlap: bool,
+    /// Extra iterations run with overlap prevention on, after `iterations`.
+    pub overlap_iterations: usize,
+    pub edge_weight_influence: f64,
+    pub jitter_tolerance: f64,
+    /// Approximate repulsion with a quadtree instead of comparing every pair.
+    pub barnes_hut: bool,
+    /// Barnes–Hut accuracy; higher is faster and rougher.
+    pub theta: f64,
+    /// Stop early once nodes move less than this fraction of the layout's
+    /// spread per iteration. Zero always runs every iteration.
+    pub convergence_threshold: f64,
+    /// Seed for the random starting positions.
+    pub seed: u64,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            enabled: true,
+            iterations: 5000,
+            gravity: 4.0,
+            scaling_ratio: 2.0,
+            lin_log: true,
+            prevent_overlap: true,
+            overlap_iterations: 200,
+            edge_weight_influence: 1.0,
+            jitter_tolerance: 1.0,
+            barnes_hut: true,
+            theta: 1.2,
+            convergence_threshold: 1e-4,
+            seed: 0,
+        }
+    }
+}
+
+/// Modularity settings. `resolution` uses Gephi's scale, where the atlas
+/// was coloured at 0.2.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommunityConfig {
+    pub enabled: bool,
+    pub resolution: f64,
+    /// Seed for the order nodes are visited in.
+    pub seed: u64,
+}
+
+impl Default for CommunityConfig {
+    fn default() -> Self {
+        CommunityConfig {
+            enabled: true,
+            resolution: 0.2,
+            seed: 0,
+        }
+    }
+}
+
+/// Strength, PageRank, betweenness and eigenvector centrality per player.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CentralityConfig {
+    pub enabled: bool,
+    /// PageRank damping factor.
+    pub damping: f64,
+    /// Estimate betweenness from paths starting at this many random players.
+    /// Zero uses every player, which is exact but slowest.
+    pub betweenness_samples: usize,
+    /// Seed for picking those players.
+    pub seed: u64,
+}
+
+impl Default for CentralityConfig {
+    fn default() -> Self {
+        CentralityConfig {
+            enabled: true,
+            damping: 0.85,
+            betweenness_samples: 0,
+            seed: 0,
+        }
+    }
+}
+
+/// Collapsing several uploads of the same raid into one report. Off by
+/// default, as the published atlas was built without it. Finding them holds
+/// every valid report's times and player ids in memory at once, about 40
+/// bytes a report plus 4 per player in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    /// Reports with intersecting times are the same raid when this share of
+    /// the smaller report's players are in both.
+    pub min_overlap: f64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig { enabled: false, min_overlap: 0.8 }
+    }
+}
+
+/// Extra graphs built from slices of the reports, written as
+/// `<output_dir>/<label>.gexf` (and `.json` when `graph_json` is set). The
+/// filters apply to each slice on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// Build one graph per month, quarter or year.
+    pub period: Option<Period>,
+    /// Named date ranges, e.g. between chapter releases.
+    pub windows: Vec<WindowConfig>,
+    pub output_dir: PathBuf,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        SnapshotConfig {
+            period: None,
+            windows: Vec::new(),
+            output_dir: PathBuf::from("snapshots"),
+        }
+    }
+}
+
+/// A named range of `YYYY-MM-DD` dates; `end` is the first day left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WindowConfig {
+    pub name: String,
+    pub start: String,
+    pub end: String,
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = read_to_string(path)
+            .map_err(|e| format!("Could not read profile {}: {}", path.display(), e))?;
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| format!("Invalid profile {}: {}", path.display(), e))?;
+        config.validate().map_err(|e| format!("Invalid profile {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    /// Checks the settings that only make sense within a range. Run on every
+    /// profile as it loads, and again once command line flags are applied.
+    pub fn validate(&self) -> Result<(), String> {
+        let ranges = [
+            ("filters.alpha", self.filters.alpha, self.filters.alpha > 0.0 && self.filters.alpha <= 1.0, "above 0 and at most 1"),
+            ("filters.quantile", self.filters.quantile, (0.0..=1.0).contains(&self.filters.quantile), "from 0 to 1"),
+            ("filters.noise_delta", self.filters.noise_delta, self.filters.noise_delta.is_finite(), "a finite number"),
+            ("dedup.min_overlap", self.dedup.min_overlap, self.dedup.min_overlap > 0.0 && self.dedup.min_overlap <= 1.0, "above 0 and at most 1"),
+            ("layout.theta", self.layout.theta, self.layout.theta >= 0.0 && self.layout.theta.is_finite(), "0 or more"),
+            ("centrality.damping", self.centrality.damping, (0.0..1.0).contains(&self.centrality.damping), "from 0 up to but not including 1"),
+        ];
+        for (name, value, valid, range) in ranges {
+            if !valid {
+                return Err(format!("{} must be {}, not {}", name, range, value));
+            }
+        }
+        if self.output.centrality_csv.is_some() && !self.centrality.enabled {
+            return Err("output.centrality_csv needs centrality scores, which are turned off".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_profiles_load() {
+        let profiles = Path::new(env!("CARGO_MANIFEST_DIR")).join("profiles");
+        for name in ["community.toml", "guild.toml"] {
+            if let Err(e) = Config::from_file(&profiles.join(name)) {
+                panic!("{}", e);
+            }
+        }
+    }
+
+    /// The validation error for the defaults with one setting changed.
+    fn invalid(change: impl FnOnce(&mut Config)) -> String {
+        let mut config = Config::default();
+        change(&mut config);
+        config.validate().unwrap_err()
+    }
+
+    #[test]
+    fn rejects_out_of_range_settings() {
+        assert!(Config::default().validate().is_ok());
+        assert!(invalid(|c| c.filters.alpha = 0.0).starts_with("filters.alpha"));
+        assert!(invalid(|c| c.filters.quantile = 1.5).starts_with("filters.quantile"));
+        assert!(invalid(|c| c.filters.noise_delta = f64::NAN).starts_with("filters.noise_delta"));
+        assert!(invalid(|c| c.dedup.min_overlap = 1.2).starts_with("dedup.min_overlap"));
+        assert!(invalid(|c| c.layout.theta = -0.5).starts_with("layout.theta"));
+        assert!(invalid(|c| c.centrality.damping = 1.0).starts_with("centrality.damping"));
+        assert!(invalid(|c| c.centrality.damping = f64::NAN).starts_with("centrality.damping"));
+        let no_scores = |c: &mut Config| {
+            c.output.centrality_csv = Some(PathBuf::from("centrality.csv"));
+            c.centrality.enabled = false;
+        };
+        assert!(invalid(no_scores).starts_with("output.centrality_csv"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let error = toml::from_str::<Config>("[filters]
+alpah = 0.1
+").unwrap_err().to_string();
+        assert!(error.contains("alpah"), "{}", error);
+    }
+}
@@ -0,0 +1,164 @@
+//! Conversions between esologs' millisecond timestamps and calendar dates,
+//! all in UTC.
+
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+pub const MS_PER_DAY: u64 = 86_400_000;
+pub const MS_PER_HOUR: u64 = 3_600_000;
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's
+/// `days_from_civil`).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `(year, month, day)` of a timestamp.
+pub fn date_of(ms: u64) -> (i64, u32, u32) {
+    civil_from_days((ms / MS_PER_DAY) as i64)
+}
+
+/// Midnight at the start of the given date.
+pub fn millis_of(year: i64, month: u32, day: u32) -> u64 {
+    (days_from_civil(year, month, day) * MS_PER_DAY as i64).max(0) as u64
+}
+
+/// `YYYY-MM-DD`.
+pub fn format_date(ms: u64) -> String {
+    let (y, m, d) = date_of(ms);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// Parses `YYYY-MM-DD` into the timestamp of its midnight.
+pub fn parse_date(text: &str) -> Result<u64, String> {
+    let invalid = || format!("Expected a YYYY-MM-DD date, got {:?}", text);
+    let mut parts = text.trim().splitn(3, '-');
+    let year: i64 = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+    let month: u32 = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+    let day: u32 = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+    Ok(millis_of(year, month, day))
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let next = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    (days_from_civil(next.0, next.1, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+/// Calendar buckets used to slice reports over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    fn months(self) -> u32 {
+        match self {
+            Period::Month => 1,
+            Period::Quarter => 3,
+            Period::Year => 12,
+        }
+    }
+
+    /// Start of the period containing `ms`.
+    pub fn start_of(self, ms: u64) -> u64 {
+        let (year, month, _) = date_of(ms);
+        let months = self.months();
+        millis_of(year, (month - 1) / months * months + 1, 1)
+    }
+
+    /// Start of the period after the one starting at `start`.
+    pub fn next(self, start: u64) -> u64 {
+        let (year, month, _) = date_of(start);
+        let total = month - 1 + self.months();
+        millis_of(year + i64::from(total / 12), total % 12 + 1, 1)
+    }
+
+    /// Short name for the period starting at `start`, e.g. `2023-04`,
+    /// `2023-Q2` or `2023`.
+    pub fn label(self, start: u64) -> String {
+        let (year, month, _) = date_of(start);
+        match self {
+            Period::Month => format!("{:04}-{:02}", year, month),
+            Period::Quarter => format!("{:04}-Q{}", year, (month - 1) / 3 + 1),
+            Period::Year => format!("{:04}", year),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "month" => Ok(Period::Month),
+            "quarter" => Ok(Period::Quarter),
+            "year" => Ok(Period::Year),
+            _ => Err(format!("Unknown period {:?}, expected month, quarter or year", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        for days in [-800_000, -1, 0, 59, 10_956, 19_358, 19_724, 800_000] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+
+    #[test]
+    fn periods_cross_the_year() {
+        let december = millis_of(2023, 12, 1);
+        assert_eq!(Period::Month.next(december), millis_of(2024, 1, 1));
+        assert_eq!(Period::Quarter.next(millis_of(2023, 10, 1)), millis_of(2024, 1, 1));
+        assert_eq!(Period::Year.next(millis_of(2023, 1, 1)), millis_of(2024, 1, 1));
+        assert_eq!(Period::Quarter.start_of(millis_of(2023, 12, 31) + MS_PER_DAY - 1), millis_of(2023, 10, 1));
+
+        assert_eq!(Period::Month.label(december), "2023-12");
+        assert_eq!(Period::Month.label(Period::Month.next(december)), "2024-01");
+        assert_eq!(Period::Quarter.label(december), "2023-Q4");
+        assert_eq!(Period::Year.label(december), "2023");
+    }
+
+    #[test]
+    fn parses_leap_days() {
+        assert_eq!(parse_date("2024-02-29"), Ok(millis_of(2024, 3, 1) - MS_PER_DAY));
+        assert_eq!(format_date(parse_date("2000-02-29").unwrap()), "2000-02-29");
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("1900-02-29").is_err());
+        assert!(parse_date("2023-13-01").is_err());
+        assert!(parse_date("2023-04").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Finding reports of the same raid. Several members of a group often each
+//! upload a log of the same run, which turns into separate report codes
+//! with intersecting time ranges and almost the same players, and would
+//! count every pair in that raid several times over.
+//!
+//! A report joins a cluster when its time intersects that of the report the
+//! cluster keeps and their player sets overlap by at least `min_overlap`
+//! (shared players over the smaller set). Each cluster keeps one report: the
+//! one with the most players, then the longest, then the first in the file.
+//! Comparing with the kept report rather than any member stops a chain of
+//! partly overlapping raids from collapsing into one.
+
+use atlas_model::Report;
+
+/// Just enough of a report to compare it with others.
+struct Entry {
+    ordinal: usize,
+    start: u64,
+    end: u64,
+    /// Range of `Deduplicator::players` holding the sorted player ids.
+    players: (usize, usize),
+}
+
+#[derive(Default)]
+pub struct Deduplicator {
+    entries: Vec<Entry>,
+    players: Vec<u32>,
+}
+
+/// Which reports to skip, by their position in the report file.
+#[derive(Debug, Clone, Default)]
+pub struct Duplicates {
+    skip: Vec<bool>,
+    /// Clusters of more than one report.
+    pub clusters: usize,
+    /// Reports skipped for being in a cluster with a better one.
+    pub removed: usize,
+}
+
+impl Duplicates {
+    pub fn contains(&self, ordinal: usize) -> bool {
+        self.skip.get(ordinal).copied().unwrap_or(false)
+    }
+}
+
+impl Deduplicator {
+    /// Adds the report at position `ordinal` in the file as a candidate.
+    pub fn add(&mut self, ordinal: usize, report: &Report) -> Result<(), String> {
+        let start = self.players.len();
+        for &player in &report.players {
+            let Ok(player) = u32::try_from(player) else {
+                self.players.truncate(start);
+                return Err(format!("Player id {} doesn't fit in 32 bits", player));
+            };
+            self.players.push(player);
+        }
+        self.players[start..].sort_unstable();
+        self.entries.push(Entry {
+            ordinal,
+            start: report.start_time,
+            end: report.end_time,
+            players: (start, self.players.len()),
+        });
+        Ok(())
+    }
+
+    fn players_of(&self, entry: &Entry) -> &[u32] {
+        &self.players[entry.players.0..entry.players.1]
+    }
+
+    /// Shared players over the size of the smaller set.
+    fn overlap(&self, a: &Entry, b: &Entry) -> f64 {
+        let (a, b) = (self.players_of(a), self.players_of(b));
+        let smaller = a.len().min(b.len());
+        if smaller == 0 {
+            return 0.0;
+        }
+        let (mut i, mut j, mut shared) = (0, 0, 0);
+        while i < a.len() && j < b.len() {
+            match a[i].cmp(&b[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        shared as f64 / smaller as f64
+    }
+
+    pub fn finish(self, min_overlap: f64) -> Duplicates {
+        let n = self.entries.len();
+        let mut cluster_of = vec![0; n];
+        let mut kept: Vec<usize> = Vec::new();
+        let mut size: Vec<usize> = Vec::new();
+
+        // Sweep in order of start time. Each report joins the running cluster
+        // whose kept report it overlaps most, or starts one of its own.
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_unstable_by_key(|&i| (self.entries[i].start, i));
+        let mut running: Vec<usize> = Vec::new();
+        for &i in &order {
+            let entry = &self.entries[i];
+            running.retain(|&c| self.entries[kept[c]].end > entry.start);
+            let joined = running
+                .iter()
+                .map(|&c| (c, self.overlap(entry, &self.entries[kept[c]])))
+                .filter(|&(_, overlap)| overlap >= min_overlap)
+                .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+            match joined {
+                Some((c, _)) => {
+                    cluster_of[i] = c;
+                    size[c] += 1;
+                    if self.rank(i) > self.rank(kept[c]) {
+                        kept[c] = i;
+                    }
+                }
+                None => {
+                    cluster_of[i] = kept.len();
+                    running.push(kept.len());
+                    kept.push(i);
+                    size.push(1);
+                }
+            }
+        }
+
+        let max_ordinal = self.entries.iter().map(|e| e.ordinal + 1).max().unwrap_or(0);
+        let mut duplicates = Duplicates { skip: vec![false; max_ordinal], ..Duplicates::default() };
+        for i in 0..n {
+            if kept[cluster_of[i]] != i {
+                duplicates.skip[self.entries[i].ordinal] = true;
+                duplicates.removed += 1;
+            }
+        }
+        duplicates.clusters = size.iter().filter(|&&s| s > 1).count();
+        duplicates
+    }
+
+    /// Which report of a cluster to keep; higher is better.
+    fn rank(&self, i: usize) -> (usize, u64, std::cmp::Reverse<usize>) {
+        let entry = &self.entries[i];
+        let players = entry.players.1 - entry.players.0;
+        (players, entry.end.saturating_sub(entry.start), std::cmp::Reverse(entry.ordinal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::report;
+
+    fn dedup(reports: &[Report], min_overlap: f64) -> Duplicates {
+        let mut dedup = Deduplicator::default();
+        for (ordinal, report) in reports.iter().enumerate() {
+            dedup.add(ordinal, report).unwrap();
+        }
+        dedup.finish(min_overlap)
+    }
+
+    #[test]
+    fn keeps_the_fullest_upload() {
+        let reports = [report(0, 1..=8, ""), report(60_000, 1..=10, ""), report(120_000, 2..=9, ""), report(7_200_000, 1..=10, "")];
+        let duplicates = dedup(&reports, 0.8);
+        assert_eq!((duplicates.clusters, duplicates.removed), (1, 2));
+        assert_eq!((0..4).map(|i| duplicates.contains(i)).collect::<Vec<_>>(), [true, false, true, false]);
+    }
+
+    #[test]
+    fn does_not_chain_partly_overlapping_raids() {
+        // b shares 8 of 10 players with a, and c 8 with b but only 6 with a:
+        // c is another raid, not a third upload of a.
+        let a = report(0, 1..=10, "");
+        let b = report(60_000, (1..=8).chain(11..=12), "");
+        let c = report(120_000, (1..=6).chain(11..=14), "");
+        let duplicates = dedup(&[a, b, c], 0.8);
+        assert_eq!((duplicates.clusters, duplicates.removed), (1, 1));
+        assert!(!duplicates.contains(0));
+        assert!(duplicates.contains(1));
+        assert!(!duplicates.contains(2));
+    }
+
+    #[test]
+    fn rejects_ids_over_32_bits() {
+        let mut dedup = Deduplicator::default();
+        assert!(dedup.add(0, &report(0, [1, 1 << 32], "")).is_err());
+        dedup.add(1, &report(0, [1, 2], "")).unwrap();
+        assert_eq!(dedup.players, [1, 2]);
+    }
+}
@@ -0,0 +1,173 @@
+//! Per-period activity of an already selected graph, for dynamic GEXF
+//! output. Each report counts towards the period its start time falls in.
+
+use std::collections::{BTreeMap, HashMap};
+use atlas_model::Report;
+
+use crate::{dates::Period, graph::AtlasGraph, weighting::{PairStats, Scheme}};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Activity {
+    pub reports: usize,
+    pub duration_ms: u64,
+    /// Newman share, for edges only.
+    pub newman: f64,
+}
+
+/// Activity of every node and edge of a graph, keyed by period start and
+/// indexed like `AtlasGraph::nodes` and `AtlasGraph::edges`. Reports are
+/// added one at a time so they can be streamed.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub period: Period,
+    pub nodes: Vec<BTreeMap<u64, Activity>>,
+    pub edges: Vec<BTreeMap<u64, Activity>>,
+    /// Reports in each period.
+    pub reports: BTreeMap<u64, usize>,
+    weighting: Scheme,
+    ends: Vec<(usize, usize)>,
+    index_of: HashMap<usize, usize>,
+    edge_of: HashMap<(usize, usize), usize>,
+    present: Vec<usize>,
+}
+
+impl Timeline {
+    pub fn new(graph: &AtlasGraph, period: Period, weighting: Scheme) -> Self {
+        let index_of = graph.nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+        let edge_of = graph
+            .edges
+            .iter()
+            .enumerate()
+            .map(|(i, e)| ((e.source.min(e.target), e.source.max(e.target)), i))
+            .collect();
+        Timeline {
+            period,
+            nodes: vec![BTreeMap::new(); graph.nodes.len()],
+            edges: vec![BTreeMap::new(); graph.edges.len()],
+            reports: BTreeMap::new(),
+            weighting,
+            ends: graph.edges.iter().map(|e| (e.source, e.target)).collect(),
+            index_of,
+            edge_of,
+            present: Vec::new(),
+        }
+    }
+
+    /// Counts `report` towards its period, with `duration_ms` already capped.
+    pub fn add(&mut self, report: &Report, duration_ms: u64) {
+        let start = self.period.start_of(report.start_time);
+        let share = 1.0 / (report.players.len() as f64 - 1.0);
+        *self.reports.entry(start).or_default() += 1;
+        self.present.clear();
+        self.present.extend(report.players.iter().filter_map(|id| self.index_of.get(id).copied()));
+        for &node in &self.present {
+            let activity: &mut Activity = self.nodes[node].entry(start).or_default();
+            activity.reports += 1;
+            activity.duration_ms += duration_ms;
+        }
+        for i in 0..self.present.len() {
+            for j in (i + 1)..self.present.len() {
+                let (a, b) = (self.present[i], self.present[j]);
+                if let Some(&edge) = self.edge_of.get(&(a.min(b), a.max(b))) {
+                    let activity: &mut Activity = self.edges[edge].entry(start).or_default();
+                    activity.reports += 1;
+                    activity.duration_ms += duration_ms;
+                    activity.newman += share;
+                }
+            }
+        }
+    }
+
+    /// Weight of edge `edge` over the period starting at `start`, by the
+    /// graph's weighting with that period's reports standing in for all of
+    /// them. Zero when the weighting has no finite answer.
+    pub fn edge_weight(&self, edge: usize, start: u64) -> f64 {
+        let Some(activity) = self.edges[edge].get(&start) else {
+            return 0.0;
+        };
+        let (a, b) = self.ends[edge];
+        let appearances = |node: usize| self.nodes[node].get(&start).map_or(0, |n| n.reports);
+        let weight = self.weighting.weighting().weight(&PairStats {
+            reports: activity.reports,
+            duration_ms: activity.duration_ms,
+            newman: activity.newman,
+            appearances: (appearances(a), appearances(b)),
+            total_reports: self.reports.get(&start).copied().unwrap_or(0),
+        });
+        if weight.is_finite() { weight } else { 0.0 }
+    }
+
+    /// Merges the active periods in `activity` into `(start, end)` spells,
+    /// joining periods that follow on from each other.
+    pub fn spells(&self, activity: &BTreeMap<u64, Activity>) -> Vec<(u64, u64)> {
+        let mut spells: Vec<(u64, u64)> = Vec::new();
+        for &start in activity.keys() {
+            let end = self.period.next(start);
+            match spells.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => spells.push((start, end)),
+            }
+        }
+        spells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dates::millis_of, graph::{AtlasEdge, AtlasNode}, testing::report};
+
+    /// Players 10, 20 and 30 in a path.
+    fn timeline(weighting: Scheme) -> Timeline {
+        let graph = AtlasGraph {
+            nodes: [10, 20, 30].into_iter().map(|id| AtlasNode { id, ..AtlasNode::default() }).collect(),
+            edges: [(0, 1), (1, 2)]
+                .into_iter()
+                .map(|(source, target)| AtlasEdge { source, target, weight: 1.0, ..AtlasEdge::default() })
+                .collect(),
+            laid_out: false,
+        };
+        Timeline::new(&graph, Period::Month, weighting)
+    }
+
+    #[test]
+    fn weighs_edges_per_period() {
+        let mut timeline = timeline(Scheme::Jaccard);
+        let january = millis_of(2024, 1, 1);
+        let february = millis_of(2024, 2, 1);
+        timeline.add(&report(january, [10, 20], "a"), 1000);
+        timeline.add(&report(january + 5, [10, 20, 30], "b"), 1000);
+        timeline.add(&report(january + 9, [10], "c"), 1000);
+        timeline.add(&report(february, [20, 30, 40], "d"), 1000);
+
+        // January: 10 and 20 share 2 of the 3 reports either was in.
+        assert!((timeline.edge_weight(0, january) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((timeline.edge_weight(1, january) - 0.5).abs() < 1e-12);
+        assert_eq!(timeline.edge_weight(0, february), 0.0);
+        assert_eq!(timeline.edge_weight(1, february), 1.0);
+        assert_eq!(timeline.reports[&january], 3);
+        assert_eq!(timeline.edges[1][&february].newman, 0.5);
+    }
+
+    #[test]
+    fn non_finite_weights_are_zero() {
+        let mut timeline = timeline(Scheme::Duration);
+        let january = millis_of(2024, 1, 1);
+        timeline.add(&report(january, [10, 20], "a"), 0);
+        assert_eq!(timeline.edge_weight(0, january), 0.0);
+    }
+
+    #[test]
+    fn spells_join_consecutive_periods() {
+        let timeline = timeline(Scheme::Count);
+        let activity: BTreeMap<u64, Activity> = [(2023, 11), (2023, 12), (2024, 1), (2024, 3)]
+            .into_iter()
+            .map(|(year, month)| (millis_of(year, month, 1), Activity::default()))
+            .collect();
+        assert_eq!(
+            timeline.spells(&activity),
+            [(millis_of(2023, 11, 1), millis_of(2024, 2, 1)), (millis_of(2024, 3, 1), millis_of(2024, 4, 1))]
+        );
+        assert!(timeline.spells(&BTreeMap::new()).is_empty());
+    }
+}
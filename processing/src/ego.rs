@@ -0,0 +1,191 @@
+//! One player's neighbourhood, taken from the full edge map rather than the
+//! pruned atlas: every partner counts, not just the heaviest 30, and the
+//! player doesn't need enough appearances to be in the atlas at all.
+
+use std::{collections::HashMap, path::Path};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    build::{self, Reports},
+    config::Config,
+    dates::MS_PER_HOUR,
+    gexf,
+    graph::{AtlasEdge, AtlasGraph, AtlasNode, DEFAULT_NODE_COLOR},
+    input, json,
+    pairs::EdgeKey,
+    query,
+};
+
+#[derive(Debug, Clone)]
+pub struct EgoOptions<'a> {
+    pub name: &'a str,
+    /// 1 for the player's partners, 2 for their partners' partners as well.
+    pub hops: usize,
+    /// Reports two players must share to count as partners.
+    pub min_reports: usize,
+    /// Partners listed.
+    pub top: usize,
+    pub gexf: &'a Path,
+    pub graph_json: Option<&'a Path>,
+}
+
+/// `ego` and everyone within `hops` of them, grown a ring at a time: a
+/// player joins when they share `min_reports` reports with someone in the
+/// previous ring.
+fn neighbourhood(
+    reports: &Reports,
+    config: &Config,
+    ego: u32,
+    hops: usize,
+    min_reports: usize,
+) -> Result<FxHashSet<u32>, Box<dyn std::error::Error>> {
+    let mut members: FxHashSet<u32> = FxHashSet::from_iter([ego]);
+    let mut ring = members.clone();
+    for _ in 0..hops {
+        let mut shared: FxHashMap<EdgeKey, usize> = FxHashMap::default();
+        reports.for_each_valid(config, None, |_, report, _| {
+            for &a in report.players.iter().filter(|&&p| ring.contains(&(p as u32))) {
+                for &b in report.players.iter().filter(|&&p| !members.contains(&(p as u32))) {
+                    *shared.entry(EdgeKey::new(a as u32, b as u32)).or_default() += 1;
+                }
+            }
+        })?;
+        ring = shared
+            .into_iter()
+            .filter(|&(_, count)| count >= min_reports)
+            .map(|(key, _)| {
+                let (a, b) = key.players();
+                if ring.contains(&a) { b } else { a }
+            })
+            .collect();
+        members.extend(&ring);
+    }
+    Ok(members)
+}
+
+pub fn run(config: &Config, options: &EgoOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let players = input::read_player_table(&config.input.players)?;
+    let ego = players
+        .iter()
+        .find(|p| p.player_name == options.name)
+        .map(|p| p.id)
+        .ok_or_else(|| query::unknown_player(options.name, players.iter().map(|p| p.player_name.as_str())))?;
+    let ego = u32::try_from(ego).map_err(|_| format!("Player id {} doesn't fit in 32 bits", ego))?;
+    let id_to_name: HashMap<usize, String> = players.into_iter().map(|p| (p.id, p.player_name)).collect();
+
+    let reports = Reports::open(config)?;
+    let appearances = build::count_appearances(&reports, config, None)?;
+
+    let members = neighbourhood(&reports, config, ego, options.hops, options.min_reports)?;
+
+    let edge_stats = build::accumulate_edges(&reports, config, None, &members, None)?;
+    let mut keys: Vec<EdgeKey> = edge_stats
+        .iter()
+        .filter(|(_, stats)| stats.reports as usize >= options.min_reports)
+        .map(|(&key, _)| key)
+        .collect();
+    keys.sort_unstable();
+
+    let mut partners: Vec<(u32, u64, u32)> = keys
+        .iter()
+        .filter_map(|&key| {
+            let (a, b) = key.players();
+            let stats = edge_stats[&key];
+            match (a == ego, b == ego) {
+                (true, _) => Some((b, stats.duration, stats.reports)),
+                (_, true) => Some((a, stats.duration, stats.reports)),
+                _ => None,
+            }
+        })
+        .collect();
+    partners.sort_by(|x, y| y.1.cmp(&x.1).then(y.2.cmp(&x.2)).then(x.0.cmp(&y.0)));
+
+    println!("{} shares {} or more reports with {} players", options.name, options.min_reports, partners.len());
+    println!("{:>5}  {:<24} {:>10} {:>8}", "rank", "partner", "hours", "reports");
+    for (rank, &(id, duration, reports)) in partners.iter().take(options.top).enumerate() {
+        let name = id_to_name.get(&(id as usize)).map_or("?", String::as_str);
+        println!("{:>5}  {:<24} {:>10.1} {:>8}", rank + 1, name, duration as f64 / MS_PER_HOUR as f64, reports);
+    }
+
+    let mut ids: Vec<u32> = members.into_iter().collect();
+    ids.sort_unstable();
+    let mut graph = AtlasGraph::default();
+    let mut index_of: FxHashMap<u32, usize> = FxHashMap::default();
+    for id in ids {
+        let (Some(name), Some(stats)) = (id_to_name.get(&(id as usize)), appearances.players.get(&id)) else {
+            continue;
+        };
+        index_of.insert(id, graph.nodes.len());
+        graph.nodes.push(AtlasNode {
+            id: id as usize,
+            label: name.clone(),
+            color: DEFAULT_NODE_COLOR.to_string(),
+            appearances: stats.appearances,
+            duration_ms: stats.duration,
+            first_seen: stats.first_seen,
+            last_seen: stats.last_seen,
+            ..AtlasNode::default()
+        });
+    }
+
+    let weighting = config.filters.weighting.weighting();
+    for key in keys {
+        let (a, b) = key.players();
+        let stats = edge_stats[&key];
+        let Some(weight) = build::edge_weight(weighting, &appearances, key, &stats) else {
+            continue;
+        };
+        if let (Some(&source), Some(&target)) = (index_of.get(&a), index_of.get(&b)) {
+            graph.edges.push(AtlasEdge { source, target, weight, duration_ms: stats.duration, reports: stats.reports as usize });
+        }
+    }
+    graph.size_by_degree();
+    println!("Ego graph has {} players and {} edges", graph.nodes.len(), graph.edges.len());
+    build::finish(&mut graph, config);
+
+    gexf::write(&graph, options.gexf)?;
+    println!("Wrote {}", options.gexf.display());
+    if let Some(path) = options.graph_json {
+        json::write(&graph, path)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+    use crate::testing::{report, temp_dir};
+
+    #[test]
+    fn partners_of_partners_need_two_hops() {
+        // 1 and 2 share 3 reports, 2 and 3 share 2, 2 and 4 share 1, and 5
+        // is only ever with 3.
+        let groups: [&[usize]; 9] = [&[1, 2], &[1, 2], &[1, 2, 4], &[2, 3], &[2, 3], &[3, 5], &[3, 5], &[3, 5], &[6, 7]];
+        let lines: String = groups
+            .iter()
+            .enumerate()
+            .map(|(i, players)| serde_json::to_string(&report(i as u64, players.iter().copied(), &i.to_string())).unwrap() + "\n")
+            .collect();
+        let dir = temp_dir("ego-rings");
+        let mut config = Config::default();
+        config.input.reports = dir.join("reports.json");
+        config.input.cache = false;
+        write(&config.input.reports, lines).unwrap();
+        let reports = Reports::open(&config).unwrap();
+
+        let members = |hops, min_reports| {
+            let mut ids: Vec<u32> = neighbourhood(&reports, &config, 1, hops, min_reports).unwrap().into_iter().collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(members(0, 2), [1]);
+        assert_eq!(members(1, 2), [1, 2]);
+        assert_eq!(members(2, 2), [1, 2, 3]);
+        assert_eq!(members(2, 3), [1, 2]);
+        assert_eq!(members(2, 1), [1, 2, 3, 4]);
+        assert_eq!(members(3, 2), [1, 2, 3, 5]);
+    }
+}
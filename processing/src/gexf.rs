@@ -0,0 +1,223 @@
+//! GEXF 1.3 output for Gephi. https://gexf.net/schema.html
+
+use std::{borrow::Cow, fs::File, io::{BufWriter, Write}, path::Path};
+
+use crate::{dates::{MS_PER_DAY, MS_PER_HOUR, format_date}, dynamic::Timeline, graph::AtlasGraph};
+
+/// Escapes text for use inside an attribute value. Characters XML 1.0 can't
+/// represent at all are dropped.
+pub fn escape(text: &str) -> Cow<'_, str> {
+    let needs_work = |c: char| matches!(c, '&' | '<' | '>' | '"' | '\'') || (c < ' ' && !matches!(c, '\t' | '\n' | '\r'));
+    if !text.chars().any(needs_work) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+pub fn write(graph: &AtlasGraph, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut output = BufWriter::new(File::create(path)?);
+    write_to(graph, None, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Writes a dynamic graph: nodes and edges exist during the periods they
+/// were active in, and carry per-period weights, so Gephi's timeline can
+/// replay the atlas.
+pub fn write_dynamic(graph: &AtlasGraph, timeline: &Timeline, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut output = BufWriter::new(File::create(path)?);
+    write_to(graph, Some(timeline), &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn hours(ms: u64) -> f64 {
+    ms as f64 / MS_PER_HOUR as f64
+}
+
+/// GEXF intervals include both ends, so a period runs up to the day before
+/// the next one starts.
+fn interval(start: u64, end: u64) -> (String, String) {
+    (format_date(start), format_date(end - MS_PER_DAY))
+}
+
+fn write_spells<W: Write>(output: &mut W, spells: &[(u64, u64)]) -> std::io::Result<()> {
+    writeln!(output, r#"        <spells>"#)?;
+    for &(start, end) in spells {
+        let (from, to) = interval(start, end);
+        writeln!(output, r#"          <spell start="{}" end="{}"/>"#, from, to)?;
+    }
+    writeln!(output, r#"        </spells>"#)
+}
+
+pub fn write_to<W: Write>(graph: &AtlasGraph, timeline: Option<&Timeline>, output: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+    let has_communities = graph.nodes.iter().any(|n| n.community.is_some());
+    let has_centrality = graph.nodes.iter().any(|n| n.centrality.is_some());
+
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        output,
+        r#"<gexf xmlns="http://gexf.net/1.3" xmlns:viz="http://gexf.net/1.3/viz" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://gexf.net/1.3 http://gexf.net/1.3/gexf.xsd" version="1.3">"#
+    )?;
+    writeln!(output, r#"  <meta>"#)?;
+    writeln!(output, r#"    <creator>raideratlas processing</creator>"#)?;
+    writeln!(output, r#"    <description>Players who raid together in The Elder Scrolls Online</description>"#)?;
+    writeln!(output, r#"  </meta>"#)?;
+    match timeline {
+        Some(_) => writeln!(output, r#"  <graph mode="dynamic" defaultedgetype="undirected" timeformat="date" timerepresentation="interval">"#)?,
+        None => writeln!(output, r#"  <graph mode="static" defaultedgetype="undirected">"#)?,
+    }
+
+    writeln!(output, r#"    <attributes class="node" mode="static">"#)?;
+    writeln!(output, r#"      <attribute id="appearances" title="Appearances" type="integer"/>"#)?;
+    writeln!(output, r#"      <attribute id="hours" title="Total Hours" type="double"/>"#)?;
+    writeln!(output, r#"      <attribute id="first_seen" title="First Seen" type="string"/>"#)?;
+    writeln!(output, r#"      <attribute id="last_seen" title="Last Seen" type="string"/>"#)?;
+    if has_communities {
+        writeln!(output, r#"      <attribute id="modularity_class" title="Modularity Class" type="integer"/>"#)?;
+    }
+    if has_centrality {
+        writeln!(output, r#"      <attribute id="strength" title="Weighted Degree" type="double"/>"#)?;
+        writeln!(output, r#"      <attribute id="pagerank" title="PageRank" type="double"/>"#)?;
+        writeln!(output, r#"      <attribute id="betweenness" title="Betweenness Centrality" type="double"/>"#)?;
+        writeln!(output, r#"      <attribute id="eigenvector" title="Eigenvector Centrality" type="double"/>"#)?;
+    }
+    writeln!(output, r#"    </attributes>"#)?;
+    writeln!(output, r#"    <attributes class="edge" mode="static">"#)?;
+    writeln!(output, r#"      <attribute id="duration_ms" title="Duration (ms)" type="long"/>"#)?;
+    writeln!(output, r#"      <attribute id="reports" title="Reports" type="integer"/>"#)?;
+    writeln!(output, r#"    </attributes>"#)?;
+    if timeline.is_some() {
+        writeln!(output, r#"    <attributes class="node" mode="dynamic">"#)?;
+        writeln!(output, r#"      <attribute id="period_appearances" title="Appearances In Period" type="integer"/>"#)?;
+        writeln!(output, r#"      <attribute id="period_hours" title="Hours In Period" type="double"/>"#)?;
+        writeln!(output, r#"    </attributes>"#)?;
+        writeln!(output, r#"    <attributes class="edge" mode="dynamic">"#)?;
+        writeln!(output, r#"      <attribute id="weight" title="Weight" type="double"/>"#)?;
+        writeln!(output, r#"      <attribute id="period_reports" title="Reports In Period" type="integer"/>"#)?;
+        writeln!(output, r#"      <attribute id="period_hours" title="Hours In Period" type="double"/>"#)?;
+        writeln!(output, r#"    </attributes>"#)?;
+    }
+
+    writeln!(output, r#"    <nodes>"#)?;
+    for (i, node) in graph.nodes.iter().enumerate() {
+        writeln!(output, r#"      <node id="{}" label="{}">"#, node.id, escape(&node.label))?;
+        writeln!(output, r#"        <attvalues>"#)?;
+        writeln!(output, r#"          <attvalue for="appearances" value="{}"/>"#, node.appearances)?;
+        writeln!(output, r#"          <attvalue for="hours" value="{:.2}"/>"#, hours(node.duration_ms))?;
+        writeln!(output, r#"          <attvalue for="first_seen" value="{}"/>"#, format_date(node.first_seen))?;
+        writeln!(output, r#"          <attvalue for="last_seen" value="{}"/>"#, format_date(node.last_seen))?;
+        if let Some(community) = node.community {
+            writeln!(output, r#"          <attvalue for="modularity_class" value="{}"/>"#, community)?;
+        }
+        if let Some(c) = node.centrality {
+            writeln!(output, r#"          <attvalue for="strength" value="{}"/>"#, c.strength)?;
+            writeln!(output, r#"          <attvalue for="pagerank" value="{}"/>"#, c.pagerank)?;
+            writeln!(output, r#"          <attvalue for="betweenness" value="{}"/>"#, c.betweenness)?;
+            writeln!(output, r#"          <attvalue for="eigenvector" value="{}"/>"#, c.eigenvector)?;
+        }
+        if let Some(timeline) = timeline {
+            for (&start, activity) in &timeline.nodes[i] {
+                let (from, to) = interval(start, timeline.period.next(start));
+                writeln!(output, r#"          <attvalue for="period_appearances" value="{}" start="{}" end="{}"/>"#, activity.reports, from, to)?;
+                writeln!(output, r#"          <attvalue for="period_hours" value="{:.2}" start="{}" end="{}"/>"#, hours(activity.duration_ms), from, to)?;
+            }
+        }
+        writeln!(output, r#"        </attvalues>"#)?;
+        if let Some(timeline) = timeline {
+            write_spells(output, &timeline.spells(&timeline.nodes[i]))?;
+        }
+        if let Ok(rgb) = u32::from_str_radix(node.color.trim_start_matches('#'), 16) {
+            writeln!(output, r#"        <viz:color r="{}" g="{}" b="{}"/>"#, rgb >> 16, (rgb >> 8) & 0xff, rgb & 0xff)?;
+        }
+        writeln!(output, r#"        <viz:size value="{}"/>"#, node.size)?;
+        if graph.laid_out {
+            writeln!(output, r#"        <viz:position x="{}" y="{}" z="0.0"/>"#, node.x, node.y)?;
+        }
+        writeln!(output, r#"      </node>"#)?;
+    }
+    writeln!(output, r#"    </nodes>"#)?;
+
+    writeln!(output, r#"    <edges>"#)?;
+    for (i, edge) in graph.edges.iter().enumerate() {
+        writeln!(
+            output,
+            r#"      <edge id="{}" source="{}" target="{}" weight="{}">"#,
+            i, graph.nodes[edge.source].id, graph.nodes[edge.target].id, edge.weight
+        )?;
+        writeln!(output, r#"        <attvalues>"#)?;
+        writeln!(output, r#"          <attvalue for="duration_ms" value="{}"/>"#, edge.duration_ms)?;
+        writeln!(output, r#"          <attvalue for="reports" value="{}"/>"#, edge.reports)?;
+        if let Some(timeline) = timeline {
+            for (&start, activity) in &timeline.edges[i] {
+                let (from, to) = interval(start, timeline.period.next(start));
+                let weight = timeline.edge_weight(i, start);
+                writeln!(output, r#"          <attvalue for="weight" value="{}" start="{}" end="{}"/>"#, weight, from, to)?;
+                writeln!(output, r#"          <attvalue for="period_reports" value="{}" start="{}" end="{}"/>"#, activity.reports, from, to)?;
+                writeln!(output, r#"          <attvalue for="period_hours" value="{:.2}" start="{}" end="{}"/>"#, hours(activity.duration_ms), from, to)?;
+            }
+        }
+        writeln!(output, r#"        </attvalues>"#)?;
+        if let Some(timeline) = timeline {
+            write_spells(output, &timeline.spells(&timeline.edges[i]))?;
+        }
+        writeln!(output, r#"      </edge>"#)?;
+    }
+    writeln!(output, r#"    </edges>"#)?;
+    writeln!(output, r#"  </graph>"#)?;
+    writeln!(output, r#"</gexf>"#)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{AtlasEdge, AtlasNode};
+
+    #[test]
+    fn escapes_markup_and_drops_control_characters() {
+        assert_eq!(escape(r#"a&b<c>d"e'f"#), "a&amp;b&lt;c&gt;d&quot;e&apos;f");
+        assert_eq!(escape("bell\u{7}null\u{0}esc\u{1b}"), "bellnullesc");
+        assert_eq!(escape("tab\tline\ncr\r"), "tab\tline\ncr\r");
+        assert!(matches!(escape("@Plain_Name ünï"), Cow::Borrowed("@Plain_Name ünï")));
+    }
+
+    #[test]
+    fn output_parses_with_awkward_labels() {
+        let labels = [r#"@a&b<"c>"#, "@it's\u{1}", "@plain"];
+        let graph = AtlasGraph {
+            nodes: labels
+                .iter()
+                .enumerate()
+                .map(|(i, &label)| AtlasNode { id: i + 1, label: label.to_string(), ..AtlasNode::default() })
+                .collect(),
+            edges: vec![AtlasEdge { source: 0, target: 1, weight: 1.5, duration_ms: 7_200_000, reports: 2 }],
+            laid_out: false,
+        };
+        let mut output = Vec::new();
+        write_to(&graph, None, &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+
+        let document = roxmltree::Document::parse(&text).expect("gexf should be well-formed XML");
+        let parsed: Vec<&str> = document
+            .descendants()
+            .filter(|n| n.has_tag_name("node"))
+            .filter_map(|n| n.attribute("label"))
+            .collect();
+        assert_eq!(parsed, [r#"@a&b<"c>"#, "@it's", "@plain"]);
+        assert_eq!(document.descendants().filter(|n| n.has_tag_name("edge")).count(), 1);
+    }
+}
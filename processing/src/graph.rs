@@ -0,0 +1,79 @@
+/// The selected graph, ready for layout and export. Edges refer to nodes by
+/// their index in `nodes`, not by player id.
+#[derive(Debug, Clone, Default)]
+pub struct AtlasGraph {
+    pub nodes: Vec<AtlasNode>,
+    pub edges: Vec<AtlasEdge>,
+    /// Whether `x`/`y` hold a layout rather than the zero default.
+    pub laid_out: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AtlasNode {
+    pub id: usize,
+    pub label: String,
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    pub community: Option<usize>,
+    /// `#rrggbb`, as the visualisation expects.
+    pub color: String,
+    /// Valid reports the player appears in.
+    pub appearances: usize,
+    /// Time spent in those reports, capped per report like edge weights.
+    pub duration_ms: u64,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub centrality: Option<Centrality>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Centrality {
+    /// Sum of edge weights.
+    pub strength: f64,
+    pub pagerank: f64,
+    /// Share of shortest paths through the node, counted in hops.
+    pub betweenness: f64,
+    pub eigenvector: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AtlasEdge {
+    pub source: usize,
+    pub target: usize,
+    pub weight: f64,
+    pub duration_ms: u64,
+    pub reports: usize,
+}
+
+/// Colour for nodes when community detection is off.
+pub const DEFAULT_NODE_COLOR: &str = "#cccccc";
+
+const MIN_NODE_SIZE: f64 = 10.0;
+const MAX_NODE_SIZE: f64 = 40.0;
+
+impl AtlasGraph {
+    pub fn degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.nodes.len()];
+        for edge in &self.edges {
+            degrees[edge.source] += 1;
+            degrees[edge.target] += 1;
+        }
+        degrees
+    }
+
+    /// Sizes nodes linearly by degree, the same way Gephi's ranking does.
+    pub fn size_by_degree(&mut self) {
+        let degrees = self.degrees();
+        let min = degrees.iter().copied().min().unwrap_or(0);
+        let max = degrees.iter().copied().max().unwrap_or(0);
+        for (node, &degree) in self.nodes.iter_mut().zip(&degrees) {
+            node.size = if max > min {
+                let t = (degree - min) as f64 / (max - min) as f64;
+                MIN_NODE_SIZE + t * (MAX_NODE_SIZE - MIN_NODE_SIZE)
+            } else {
+                MIN_NODE_SIZE
+            };
+        }
+    }
+}
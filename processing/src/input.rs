@@ -0,0 +1,246 @@
+//! Reading the collection output. Reports are streamed one at a time: the
+//! full report file is several hundred megabytes and is never held in memory.
+//!
+//! The report file has one JSON object per line. Normally a malformed line
+//! fails the read; with a rejects log it is skipped and logged instead, so a
+//! half-written last line from an interrupted collection run isn't fatal.
+
+use std::{
+    collections::HashMap,
+    fs::{File, create_dir_all},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use atlas_model::{Player, Report};
+
+use crate::cache::{self, CacheWriter, Hash};
+
+/// The player table in file order.
+pub fn read_player_table(path: &Path) -> Result<Vec<Player>, Box<dyn std::error::Error>> {
+    let mut rdr = csv::Reader::from_reader(File::open(path)?);
+    let mut players = Vec::new();
+    for result in rdr.deserialize::<Player>() {
+        players.push(result?);
+    }
+    Ok(players)
+}
+
+pub fn read_players(path: &Path) -> Result<HashMap<usize, String>, Box<dyn std::error::Error>> {
+    Ok(read_player_table(path)?.into_iter().map(|p| (p.id, p.player_name)).collect())
+}
+
+/// Lines skipped by lenient reading, logged as `path:line: error`.
+pub struct Rejects {
+    path: PathBuf,
+    output: BufWriter<File>,
+    count: usize,
+}
+
+impl Rejects {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Rejects { path: path.to_path_buf(), output: BufWriter::new(File::create(path)?), count: 0 })
+    }
+
+    fn record(&mut self, source: &Path, line: usize, error: &serde_json::Error) -> io::Result<()> {
+        self.count += 1;
+        writeln!(self.output, "{}:{}: {}", source.display(), line, error)
+    }
+
+    /// Flushes the log, says how many lines were skipped and returns that.
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.output.flush()?;
+        if self.count > 0 {
+            println!("Skipped {} malformed report lines, listed in {}", self.count, self.path.display());
+        }
+        Ok(self.count)
+    }
+}
+
+/// Calls `f` with every report in the file in order and returns how many
+/// there were. With `rejects`, malformed lines are logged there and skipped
+/// rather than failing the read.
+pub fn for_each_report(
+    path: &Path,
+    rejects: Option<&mut Rejects>,
+    f: impl FnMut(&Report),
+) -> Result<usize, Box<dyn std::error::Error>> {
+    match rejects {
+        Some(rejects) => for_each_report_with(path, |line, e| Ok(rejects.record(path, line, &e)?), f),
+        None => for_each_report_with(
+            path,
+            |line, e| Err(format!("{} line {}: {} (lenient reading skips malformed lines)", path.display(), line, e).into()),
+            f,
+        ),
+    }
+}
+
+/// Like `for_each_report`, handing each malformed line's number and parse
+/// error to `on_error`, which decides whether the read goes on.
+pub fn for_each_report_with(
+    path: &Path,
+    mut on_error: impl FnMut(usize, serde_json::Error) -> Result<(), Box<dyn std::error::Error>>,
+    mut f: impl FnMut(&Report),
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let (mut line, mut count) = (0, 0);
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(count);
+        }
+        line += 1;
+        if buf.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        match serde_json::from_slice::<Report>(&buf) {
+            Ok(report) => {
+                f(&report);
+                count += 1;
+            }
+            Err(e) => on_error(line, e)?,
+        }
+    }
+}
+
+/// A report file, read from its binary cache when it has one.
+pub struct ReportSource {
+    path: PathBuf,
+    hash: Hash,
+    cache: Option<PathBuf>,
+    /// Skip malformed lines when reading the JSON.
+    lenient: bool,
+}
+
+impl ReportSource {
+    /// With a `cache_dir`, finds the cache for the report file's current
+    /// contents there, writing it first if it doesn't exist yet. With a
+    /// `rejects` path the file is read leniently, and its malformed lines are
+    /// logged there once, up front. A file with malformed lines is never
+    /// cached, so a later strict run still sees them, and the log is left
+    /// empty when the cache is used.
+    pub fn open(path: &Path, cache_dir: Option<&Path>, rejects: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let hash = cache::hash_file(path)?;
+        let mut source = ReportSource { path: path.to_path_buf(), hash, cache: None, lenient: rejects.is_some() };
+
+        let cache_path = cache_dir.map(|dir| cache::path_for(dir, &hash));
+        if let Some(cache_path) = &cache_path
+            && cache::open(cache_path, &hash)?.is_some()
+        {
+            println!("Using report cache {}", cache_path.display());
+            if let Some(rejects) = rejects {
+                Rejects::create(rejects)?.finish()?;
+            }
+            source.cache = Some(cache_path.clone());
+            return Ok(source);
+        }
+        if cache_path.is_none() && rejects.is_none() {
+            return Ok(source);
+        }
+
+        let mut writer = match (cache_dir, &cache_path) {
+            (Some(dir), Some(cache_path)) => {
+                create_dir_all(dir)?;
+                Some(CacheWriter::create(cache_path, &hash)?)
+            }
+            _ => None,
+        };
+        let mut rejects = rejects.map(Rejects::create).transpose()?;
+        let mut result = Ok(());
+        for_each_report(path, rejects.as_mut(), |report| {
+            if let Some(writer) = writer.as_mut()
+                && result.is_ok()
+            {
+                result = writer.write(report);
+            }
+        })?;
+        result?;
+        let rejected = rejects.map(Rejects::finish).transpose()?.unwrap_or(0);
+
+        if let (Some(writer), Some(cache_path)) = (writer, cache_path) {
+            if rejected == 0 {
+                writer.finish()?;
+                println!("Wrote report cache {}", cache_path.display());
+                source.cache = Some(cache_path);
+            } else {
+                writer.discard()?;
+                println!("Not caching {}, it has malformed lines", path.display());
+            }
+        }
+        Ok(source)
+    }
+
+    /// BLAKE3 hash of the report file.
+    pub fn hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn for_each(&self, f: impl FnMut(&Report)) -> Result<usize, Box<dyn std::error::Error>> {
+        match &self.cache {
+            Some(path) => {
+                let input = cache::open(path, &self.hash)?
+                    .ok_or_else(|| format!("Report cache {} changed during the run", path.display()))?;
+                Ok(cache::for_each(input, f)?)
+            }
+            // Malformed lines were already logged when the source was opened.
+            None if self.lenient => for_each_report_with(&self.path, |_, _| Ok(()), f),
+            None => for_each_report(&self.path, None, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, write};
+
+    use super::*;
+    use crate::testing::temp_dir;
+
+    const GOOD: &str = r#"{"startTime":0,"endTime":3600000,"players":[1,2],"code":"a"}"#;
+
+    #[test]
+    fn blank_lines_still_count() {
+        let dir = temp_dir("input-blank-lines");
+        let path = dir.join("reports.json");
+        write(&path, format!("{}\n\n   \n{{broken\n{}\n", GOOD, GOOD)).unwrap();
+
+        let mut bad_lines = Vec::new();
+        let on_error = |line, _| {
+            bad_lines.push(line);
+            Ok(())
+        };
+        let count = for_each_report_with(&path, on_error, |_| {}).unwrap();
+        assert_eq!((count, bad_lines), (2, vec![4]));
+
+        let error = for_each_report(&path, None, |_| {}).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("{} line 4: ", path.display())), "{}", error);
+        assert!(error.ends_with("(lenient reading skips malformed lines)"), "{}", error);
+    }
+
+    #[test]
+    fn lenient_reads_are_not_cached() {
+        let dir = temp_dir("input-lenient");
+        let (path, cache_dir, rejects) = (dir.join("reports.json"), dir.join("cache"), dir.join("rejects.txt"));
+        write(&path, format!("{}\n{{broken\n", GOOD)).unwrap();
+
+        let source = ReportSource::open(&path, Some(&cache_dir), Some(&rejects)).unwrap();
+        assert!(source.cache.is_none());
+        assert!(!cache::path_for(&cache_dir, source.hash()).exists());
+        assert!(read_to_string(&rejects).unwrap().starts_with(&format!("{}:2: ", path.display())));
+        assert_eq!(source.for_each(|_| {}).unwrap(), 1);
+        assert!(ReportSource::open(&path, Some(&cache_dir), None).is_err());
+    }
+
+    #[test]
+    fn cache_hit_clears_old_rejects() {
+        let dir = temp_dir("input-cache-hit");
+        let (path, cache_dir, rejects) = (dir.join("reports.json"), dir.join("cache"), dir.join("rejects.txt"));
+        write(&path, format!("{}\n", GOOD)).unwrap();
+        assert!(ReportSource::open(&path, Some(&cache_dir), None).unwrap().cache.is_some());
+
+        write(&rejects, "stale\n").unwrap();
+        let source = ReportSource::open(&path, Some(&cache_dir), Some(&rejects)).unwrap();
+        assert!(source.cache.is_some());
+        assert_eq!(read_to_string(&rejects).unwrap(), "");
+    }
+}
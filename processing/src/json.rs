@@ -0,0 +1,45 @@
+//! Writes the graph in the JSONExporter layout that `visualisation` embeds
+//! as `static/graph`.
+
+use std::{fs::File, io::{BufWriter, Write}, path::Path};
+use atlas_model::graph::{Edge, EdgeAttributes, Graph, Node, NodeAttributes};
+
+use crate::graph::AtlasGraph;
+
+pub fn to_model(graph: &AtlasGraph) -> Graph {
+    let nodes = graph
+        .nodes
+        .iter()
+        .map(|node| Node {
+            key: node.id.to_string(),
+            attributes: NodeAttributes {
+                label: node.label.clone(),
+                x: node.x,
+                y: node.y,
+                size: node.size as f32,
+                color: node.color.clone(),
+            },
+        })
+        .collect();
+
+    let edges = graph
+        .edges
+        .iter()
+        .enumerate()
+        .map(|(i, edge)| Edge {
+            key: i.to_string(),
+            source: graph.nodes[edge.source].id.to_string(),
+            target: graph.nodes[edge.target].id.to_string(),
+            attributes: EdgeAttributes { weight: edge.weight },
+        })
+        .collect();
+
+    Graph { nodes, edges }
+}
+
+pub fn write(graph: &AtlasGraph, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut output = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut output, &to_model(graph))?;
+    output.flush()?;
+    Ok(())
+}
@@ -0,0 +1,345 @@
+//! ForceAtlas2, following Gephi's implementation (Jacomy et al. 2014) so that
+//! profiles tuned in Gephi carry over.
+
+use crate::{config::LayoutConfig, graph::{AtlasEdge, AtlasGraph}, rng::Rng};
+use self::barnes_hut::QuadTree;
+
+mod barnes_hut;
+
+/// Steps in a row that must stay under the convergence threshold before the
+/// layout is considered settled.
+const SETTLED_STEPS: usize = 10;
+
+#[derive(Debug, Clone, Default)]
+struct Body {
+    x: f64,
+    y: f64,
+    dx: f64,
+    dy: f64,
+    old_dx: f64,
+    old_dy: f64,
+    mass: f64,
+    size: f64,
+}
+
+pub struct ForceAtlas2<'a> {
+    settings: &'a LayoutConfig,
+    edges: &'a [AtlasEdge],
+    bodies: Vec<Body>,
+    adjust_sizes: bool,
+    speed: f64,
+    speed_efficiency: f64,
+}
+
+impl<'a> ForceAtlas2<'a> {
+    /// Scatters the nodes over a square using `settings.seed`.
+    pub fn new(graph: &'a AtlasGraph, settings: &'a LayoutConfig) -> Self {
+        let mut rng = Rng::new(settings.seed);
+        let spread = 10.0 * (graph.nodes.len() as f64).sqrt();
+        let mut bodies: Vec<Body> = graph
+            .nodes
+            .iter()
+            .map(|node| Body {
+                x: (rng.next_f64() - 0.5) * 2.0 * spread,
+                y: (rng.next_f64() - 0.5) * 2.0 * spread,
+                mass: 1.0,
+                size: node.size,
+                ..Body::default()
+            })
+            .collect();
+        for edge in &graph.edges {
+            bodies[edge.source].mass += 1.0;
+            bodies[edge.target].mass += 1.0;
+        }
+
+        ForceAtlas2 {
+            settings,
+            edges: &graph.edges,
+            bodies,
+            adjust_sizes: false,
+            speed: 1.0,
+            speed_efficiency: 1.0,
+        }
+    }
+
+    /// Turns Gephi's "Prevent Overlap" on or off for the following steps.
+    pub fn set_adjust_sizes(&mut self, adjust_sizes: bool) {
+        self.adjust_sizes = adjust_sizes;
+    }
+
+    /// Runs one iteration and returns how far nodes moved on average,
+    /// relative to the size of the layout.
+    pub fn step(&mut self) -> f64 {
+        for body in &mut self.bodies {
+            body.old_dx = body.dx;
+            body.old_dy = body.dy;
+            body.dx = 0.0;
+            body.dy = 0.0;
+        }
+
+        self.apply_repulsion();
+        self.apply_gravity();
+        self.apply_attraction();
+        self.adjust_speed();
+        self.move_bodies()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.bodies.iter().map(|b| (b.x, b.y))
+    }
+
+    fn apply_repulsion(&mut self) {
+        if self.settings.barnes_hut {
+            self.apply_repulsion_barnes_hut();
+        } else {
+            self.apply_repulsion_exact();
+        }
+    }
+
+    fn apply_repulsion_barnes_hut(&mut self) {
+        let k = self.settings.scaling_ratio;
+        let theta = self.settings.theta;
+        let tree = QuadTree::build(&self.bodies);
+        let mut stack = Vec::new();
+        let forces: Vec<(f64, f64)> = (0..self.bodies.len())
+            .map(|i| tree.repulsion(&self.bodies, i, k, theta, self.adjust_sizes, &mut stack))
+            .collect();
+        for (body, (fx, fy)) in self.bodies.iter_mut().zip(forces) {
+            body.dx += fx;
+            body.dy += fy;
+        }
+    }
+
+    fn apply_repulsion_exact(&mut self) {
+        let k = self.settings.scaling_ratio;
+        for i in 0..self.bodies.len() {
+            for j in (i + 1)..self.bodies.len() {
+                let (fx, fy) = repulsion(&self.bodies[i], &self.bodies[j], k, self.adjust_sizes);
+                self.bodies[i].dx += fx;
+                self.bodies[i].dy += fy;
+                self.bodies[j].dx -= fx;
+                self.bodies[j].dy -= fy;
+            }
+        }
+    }
+
+    fn apply_gravity(&mut self) {
+        let gravity = self.settings.gravity;
+        for body in &mut self.bodies {
+            let distance = (body.x * body.x + body.y * body.y).sqrt();
+            if distance > 0.0 {
+                let factor = body.mass * gravity / distance;
+                body.dx -= body.x * factor;
+                body.dy -= body.y * factor;
+            }
+        }
+    }
+
+    fn apply_attraction(&mut self) {
+        let influence = self.settings.edge_weight_influence;
+        for edge in self.edges {
+            let weight = if influence == 0.0 {
+                1.0
+            } else if influence == 1.0 {
+                edge.weight
+            } else {
+                edge.weight.powf(influence)
+            };
+
+            let (s, t) = (&self.bodies[edge.source], &self.bodies[edge.target]);
+            let x_dist = s.x - t.x;
+            let y_dist = s.y - t.y;
+            let mut distance = (x_dist * x_dist + y_dist * y_dist).sqrt();
+            if self.adjust_sizes {
+                distance -= s.size + t.size;
+            }
+            if distance <= 0.0 {
+                continue;
+            }
+
+            let factor = if self.settings.lin_log {
+                -weight * (1.0 + distance).ln() / distance
+            } else {
+                -weight
+            };
+            self.bodies[edge.source].dx += x_dist * factor;
+            self.bodies[edge.source].dy += y_dist * factor;
+            self.bodies[edge.target].dx -= x_dist * factor;
+            self.bodies[edge.target].dy -= y_dist * factor;
+        }
+    }
+
+    /// Gephi's adaptive speed: slow down when nodes swing back and forth,
+    /// speed up while they move steadily in one direction.
+    fn adjust_speed(&mut self) {
+        let mut total_swinging = 0.0;
+        let mut total_traction = 0.0;
+        for b in &self.bodies {
+            total_swinging += b.mass * ((b.old_dx - b.dx).powi(2) + (b.old_dy - b.dy).powi(2)).sqrt();
+            total_traction += b.mass * 0.5 * ((b.old_dx + b.dx).powi(2) + (b.old_dy + b.dy).powi(2)).sqrt();
+        }
+        if total_swinging <= 0.0 {
+            return;
+        }
+
+        let n = self.bodies.len() as f64;
+        let estimated_jitter = 0.05 * n.sqrt();
+        let min_jitter = estimated_jitter.sqrt();
+        let max_jitter = 10.0;
+        let mut jitter = self.settings.jitter_tolerance
+            * (estimated_jitter * total_traction / (n * n)).clamp(min_jitter, max_jitter);
+
+        let min_speed_efficiency = 0.05;
+        if total_swinging / total_traction > 2.0 {
+            if self.speed_efficiency > min_speed_efficiency {
+                self.speed_efficiency *= 0.5;
+            }
+            jitter = jitter.max(self.settings.jitter_tolerance);
+        }
+
+        let target_speed = jitter * self.speed_efficiency * total_traction / total_swinging;
+        if total_swinging > jitter * total_traction {
+            if self.speed_efficiency > min_speed_efficiency {
+                self.speed_efficiency *= 0.7;
+            }
+        } else if self.speed < 1000.0 {
+            self.speed_efficiency *= 1.3;
+        }
+
+        let max_rise = 0.5;
+        self.speed += (target_speed - self.speed).min(max_rise * self.speed);
+    }
+
+    fn move_bodies(&mut self) -> f64 {
+        let mut moved = 0.0;
+        for b in &mut self.bodies {
+            let swinging = b.mass * ((b.old_dx - b.dx).powi(2) + (b.old_dy - b.dy).powi(2)).sqrt();
+            let mut factor = self.speed / (1.0 + (self.speed * swinging).sqrt());
+            if self.adjust_sizes {
+                let force = (b.dx * b.dx + b.dy * b.dy).sqrt();
+                if force == 0.0 {
+                    continue;
+                }
+                factor = (0.1 * factor * force).min(10.0) / force;
+            }
+            b.x += b.dx * factor;
+            b.y += b.dy * factor;
+            moved += factor * (b.dx * b.dx + b.dy * b.dy).sqrt();
+        }
+
+        let n = self.bodies.len().max(1) as f64;
+        let (cx, cy) = self.bodies.iter().fold((0.0, 0.0), |(x, y), b| (x + b.x / n, y + b.y / n));
+        let spread = (self.bodies.iter().map(|b| (b.x - cx).powi(2) + (b.y - cy).powi(2)).sum::<f64>() / n).sqrt();
+        if spread > 0.0 { moved / n / spread } else { 0.0 }
+    }
+}
+
+/// Force on `a` from `b`; `b` receives the opposite.
+fn repulsion(a: &Body, b: &Body, coefficient: f64, adjust_sizes: bool) -> (f64, f64) {
+    let x_dist = a.x - b.x;
+    let y_dist = a.y - b.y;
+    let distance_sq = x_dist * x_dist + y_dist * y_dist;
+    let factor = if adjust_sizes {
+        let distance = distance_sq.sqrt() - a.size - b.size;
+        if distance > 0.0 {
+            coefficient * a.mass * b.mass / (distance * distance)
+        } else if distance < 0.0 {
+            100.0 * coefficient * a.mass * b.mass
+        } else {
+            0.0
+        }
+    } else if distance_sq > 0.0 {
+        coefficient * a.mass * b.mass / distance_sq
+    } else {
+        0.0
+    };
+    (x_dist * factor, y_dist * factor)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutStats {
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+/// Steps until the layout has settled or `max_iterations` have run.
+pub fn run_until_settled(layout: &mut ForceAtlas2, max_iterations: usize, threshold: f64) -> LayoutStats {
+    let mut settled = 0;
+    for iteration in 1..=max_iterations {
+        if layout.step() < threshold {
+            settled += 1;
+            if settled >= SETTLED_STEPS {
+                return LayoutStats { iterations: iteration, converged: true };
+            }
+        } else {
+            settled = 0;
+        }
+    }
+    LayoutStats { iterations: max_iterations, converged: false }
+}
+
+/// Lays out `graph` in place. Overlap prevention, when enabled, runs as a
+/// separate final phase since it stalls the layout if used from the start.
+pub fn run(graph: &mut AtlasGraph, settings: &LayoutConfig) {
+    let positions: Vec<(f64, f64)> = {
+        let mut layout = ForceAtlas2::new(graph, settings);
+        let stats = run_until_settled(&mut layout, settings.iterations, settings.convergence_threshold);
+        report("ForceAtlas2", stats);
+        if settings.prevent_overlap {
+            layout.set_adjust_sizes(true);
+            let stats = run_until_settled(&mut layout, settings.overlap_iterations, settings.convergence_threshold);
+            report("Prevent overlap", stats);
+        }
+        layout.positions().collect()
+    };
+
+    for (node, (x, y)) in graph.nodes.iter_mut().zip(positions) {
+        node.x = x;
+        node.y = y;
+    }
+    graph.laid_out = true;
+}
+
+fn report(phase: &str, stats: LayoutStats) {
+    if stats.converged {
+        println!("{} settled after {} iterations", phase, stats.iterations);
+    } else {
+        println!("{} stopped after {} iterations without settling", phase, stats.iterations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::AtlasNode;
+
+    /// Two triangles joined by one edge.
+    fn graph() -> AtlasGraph {
+        let edges = [(0, 1), (1, 2), (0, 2), (3, 4), (4, 5), (3, 5), (2, 3)];
+        AtlasGraph {
+            nodes: (0..6).map(|id| AtlasNode { id, size: 10.0, ..AtlasNode::default() }).collect(),
+            edges: edges
+                .iter()
+                .map(|&(source, target)| AtlasEdge { source, target, weight: 1.0, ..AtlasEdge::default() })
+                .collect(),
+            laid_out: false,
+        }
+    }
+
+    #[test]
+    fn reports_whether_the_layout_settled() {
+        let graph = graph();
+        for barnes_hut in [false, true] {
+            let settings = LayoutConfig { barnes_hut, ..LayoutConfig::default() };
+            let mut layout = ForceAtlas2::new(&graph, &settings);
+            let stats = run_until_settled(&mut layout, 5000, 1e-2);
+            assert!(stats.converged, "stopped after {} iterations", stats.iterations);
+            assert!(stats.iterations >= SETTLED_STEPS && stats.iterations < 5000);
+            assert!(layout.positions().all(|(x, y)| x.is_finite() && y.is_finite()));
+
+            let stats = run_until_settled(&mut layout, 50, 0.0);
+            assert!(!stats.converged);
+            assert_eq!(stats.iterations, 50);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Barnes–Hut approximation of ForceAtlas2's repulsion. Groups of nodes that
+//! are far away compared to their spread repel as a single body placed at
+//! their centre of mass, which takes a step from O(n²) to O(n log n).
+
+use super::{Body, repulsion};
+
+/// Below this depth a cell stops splitting and keeps all its bodies, which
+/// only happens when many nodes sit on (almost) the same point.
+const MAX_DEPTH: usize = 48;
+
+#[derive(Debug)]
+struct Cell {
+    x0: f64,
+    y0: f64,
+    width: f64,
+    mass: f64,
+    mass_x: f64,
+    mass_y: f64,
+    /// Range of `QuadTree::order` holding the bodies inside this cell.
+    start: usize,
+    end: usize,
+    /// Indices into `QuadTree::cells`; 0 means no child, since the root can
+    /// never be one.
+    children: [usize; 4],
+}
+
+impl Cell {
+    fn is_leaf(&self) -> bool {
+        self.children == [0; 4]
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x0 && x <= self.x0 + self.width && y >= self.y0 && y <= self.y0 + self.width
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct QuadTree {
+    cells: Vec<Cell>,
+    order: Vec<usize>,
+}
+
+impl QuadTree {
+    pub(super) fn build(bodies: &[Body]) -> Self {
+        let mut tree = QuadTree {
+            cells: Vec::with_capacity(bodies.len() * 2),
+            order: (0..bodies.len()).collect(),
+        };
+        if bodies.is_empty() {
+            return tree;
+        }
+
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for b in bodies {
+            min_x = min_x.min(b.x);
+            min_y = min_y.min(b.y);
+            max_x = max_x.max(b.x);
+            max_y = max_y.max(b.y);
+        }
+        let width = (max_x - min_x).max(max_y - min_y).max(f64::EPSILON);
+
+        tree.build_cell(bodies, 0, bodies.len(), min_x, min_y, width, 0);
+        tree
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build_cell(&mut self, bodies: &[Body], start: usize, end: usize, x0: f64, y0: f64, width: f64, depth: usize) -> usize {
+        let (mut mass, mut mass_x, mut mass_y) = (0.0, 0.0, 0.0);
+        for &i in &self.order[start..end] {
+            let b = &bodies[i];
+            mass += b.mass;
+            mass_x += b.x * b.mass;
+            mass_y += b.y * b.mass;
+        }
+
+        let index = self.cells.len();
+        self.cells.push(Cell {
+            x0,
+            y0,
+            width,
+            mass,
+            mass_x: mass_x / mass,
+            mass_y: mass_y / mass,
+            start,
+            end,
+            children: [0; 4],
+        });
+        if end - start <= 1 || depth >= MAX_DEPTH {
+            return index;
+        }
+
+        let half = width / 2.0;
+        let (cx, cy) = (x0 + half, y0 + half);
+        let mid = start + partition(&mut self.order[start..end], |i| bodies[i].x < cx);
+        let low_mid = start + partition(&mut self.order[start..mid], |i| bodies[i].y < cy);
+        let high_mid = mid + partition(&mut self.order[mid..end], |i| bodies[i].y < cy);
+
+        let quadrants = [
+            (start, low_mid, x0, y0),
+            (low_mid, mid, x0, cy),
+            (mid, high_mid, cx, y0),
+            (high_mid, end, cx, cy),
+        ];
+        for (q, (s, e, qx, qy)) in quadrants.into_iter().enumerate() {
+            if s < e {
+                let child = self.build_cell(bodies, s, e, qx, qy, half, depth + 1);
+                self.cells[index].children[q] = child;
+            }
+        }
+        index
+    }
+
+    /// Approximate repulsion on body `i` from every other body. A cell is
+    /// treated as one body when `distance * theta > width`, Gephi's criterion.
+    pub(super) fn repulsion(&self, bodies: &[Body], i: usize, coefficient: f64, theta: f64, adjust_sizes: bool, stack: &mut Vec<usize>) -> (f64, f64) {
+        let body = &bodies[i];
+        let (mut fx, mut fy) = (0.0, 0.0);
+        stack.clear();
+        if !self.cells.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(c) = stack.pop() {
+            let cell = &self.cells[c];
+            if cell.is_leaf() {
+                for &j in &self.order[cell.start..cell.end] {
+                    if j != i {
+                        let (x, y) = repulsion(body, &bodies[j], coefficient, adjust_sizes);
+                        fx += x;
+                        fy += y;
+                    }
+                }
+                continue;
+            }
+
+            let x_dist = body.x - cell.mass_x;
+            let y_dist = body.y - cell.mass_y;
+            let distance = (x_dist * x_dist + y_dist * y_dist).sqrt();
+            if distance * theta > cell.width && !cell.contains(body.x, body.y) {
+                let factor = coefficient * body.mass * cell.mass / (distance * distance);
+                fx += x_dist * factor;
+                fy += y_dist * factor;
+            } else {
+                stack.extend(cell.children.iter().copied().filter(|&child| child != 0));
+            }
+        }
+        (fx, fy)
+    }
+}
+
+/// Moves the entries matching `pred` to the front and returns how many there
+/// are.
+fn partition(items: &mut [usize], pred: impl Fn(usize) -> bool) -> usize {
+    let mut split = 0;
+    for k in 0..items.len() {
+        if pred(items[k]) {
+            items.swap(split, k);
+            split += 1;
+        }
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn bodies(n: usize) -> Vec<Body> {
+        let mut rng = Rng::new(3);
+        (0..n)
+            .map(|_| Body {
+                x: (rng.next_f64() - 0.5) * 200.0,
+                y: (rng.next_f64() - 0.5) * 200.0,
+                mass: 1.0 + (rng.next_u64() % 5) as f64,
+                size: 1.0,
+                ..Body::default()
+            })
+            .collect()
+    }
+
+    /// Summed difference from the exact forces over the summed exact forces.
+    fn relative_error(bodies: &[Body], theta: f64) -> f64 {
+        let tree = QuadTree::build(bodies);
+        let mut stack = Vec::new();
+        let (mut error, mut total) = (0.0, 0.0);
+        for i in 0..bodies.len() {
+            let (mut ex, mut ey) = (0.0, 0.0);
+            for j in (0..bodies.len()).filter(|&j| j != i) {
+                let (x, y) = repulsion(&bodies[i], &bodies[j], 2.0, false);
+                ex += x;
+                ey += y;
+            }
+            let (ax, ay) = tree.repulsion(bodies, i, 2.0, theta, false, &mut stack);
+            error += (ax - ex).hypot(ay - ey);
+            total += ex.hypot(ey);
+        }
+        error / total
+    }
+
+    #[test]
+    fn approximates_the_exact_repulsion() {
+        let bodies = bodies(300);
+        assert!(relative_error(&bodies, 0.0) < 1e-12);
+        let close = relative_error(&bodies, 0.5);
+        let rough = relative_error(&bodies, 1.2);
+        assert!(close < 0.01, "theta 0.5 is {close:.4} off");
+        assert!(rough < 0.05, "theta 1.2 is {rough:.4} off");
+        assert!(close < rough);
+    }
+}
@@ -0,0 +1,30 @@
+//! The processing pipeline: reports in, laid out graph out. The `processing`
+//! binary is a thin command line wrapper around this library.
+
+pub mod activity;
+pub mod backbone;
+pub mod build;
+pub mod cache;
+pub mod centrality;
+pub mod community;
+pub mod config;
+pub mod dates;
+pub mod dedup;
+pub mod dynamic;
+pub mod ego;
+pub mod gexf;
+pub mod graph;
+pub mod input;
+pub mod json;
+pub mod layout;
+pub mod manifest;
+pub mod merge;
+pub mod pairs;
+pub mod query;
+pub mod rng;
+pub mod snapshot;
+pub mod statics;
+#[cfg(test)]
+mod testing;
+pub mod validate;
+pub mod weighting;
@@ -0,0 +1,42 @@
+//! A record of what went into a build and what came out, written next to
+//! the outputs. Builds are reproducible, so two manifests with the same
+//! inputs and config list the same output hashes.
+
+use std::{fs::File, io::{BufWriter, Write}, path::{Path, PathBuf}};
+use serde::Serialize;
+
+use crate::{cache::{self, Hash}, config::Config};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRecord {
+    pub path: PathBuf,
+    pub blake3: String,
+}
+
+impl FileRecord {
+    pub fn new(path: &Path, hash: &Hash) -> Self {
+        FileRecord { path: path.to_path_buf(), blake3: cache::hex(hash) }
+    }
+
+    pub fn hash(path: &Path) -> std::io::Result<Self> {
+        Ok(FileRecord::new(path, &cache::hash_file(path)?))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest<'a> {
+    pub version: &'static str,
+    pub inputs: Vec<FileRecord>,
+    pub config: &'a Config,
+    pub outputs: Vec<FileRecord>,
+}
+
+impl Manifest<'_> {
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut output = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut output, self)?;
+        writeln!(output)?;
+        output.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+//! Combining several collection runs into one dataset. Each run numbers its
+//! players from scratch, so players are matched up by name and given new
+//! ids, and reports that more than one run fetched are collapsed by code.
+
+use std::{
+    collections::HashMap,
+    fs::{File, create_dir_all},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use atlas_model::{Player, Report};
+
+use crate::input::{self, Rejects};
+
+pub const PLAYERS_FILE: &str = "player_table.csv";
+pub const REPORTS_FILE: &str = "report_details.json";
+pub const MERGED_PLAYERS_FILE: &str = "merged_players.csv";
+pub const MERGED_REPORTS_FILE: &str = "merged_reports.json";
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeSummary {
+    pub datasets: usize,
+    pub players_read: usize,
+    pub players_written: usize,
+    /// Players whose id in the merged table differs from their id in the
+    /// dataset they came from.
+    pub ids_remapped: usize,
+    pub reports_read: usize,
+    pub reports_written: usize,
+    /// Reports dropped because an earlier one had the same code.
+    pub codes_collapsed: usize,
+    /// Player ids in reports that their dataset's player table doesn't have.
+    pub unknown_players: usize,
+    /// Malformed report lines skipped by lenient reading.
+    pub lines_rejected: usize,
+}
+
+/// Players from every dataset, with ids from 1 in order of first appearance.
+#[derive(Debug, Default)]
+struct PlayerTable {
+    players: Vec<Player>,
+    by_name: HashMap<String, usize>,
+}
+
+impl PlayerTable {
+    fn id_for(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.by_name.get(name) {
+            return id;
+        }
+        let id = self.players.len() + 1;
+        self.by_name.insert(name.to_string(), id);
+        self.players.push(Player { id, player_name: name.to_string() });
+        id
+    }
+}
+
+/// Reports by code, in order of first appearance.
+#[derive(Debug, Default)]
+struct ReportTable {
+    reports: Vec<Report>,
+    by_code: HashMap<String, usize>,
+}
+
+impl ReportTable {
+    /// Adds `report`, or widens the report already stored under its code to
+    /// cover both. Returns whether the code was new.
+    fn insert(&mut self, report: Report) -> bool {
+        match self.by_code.get(&report.code) {
+            Some(&i) => {
+                let existing = &mut self.reports[i];
+                existing.start_time = existing.start_time.min(report.start_time);
+                existing.end_time = existing.end_time.max(report.end_time);
+                existing.players.extend(report.players);
+                existing.players.sort_unstable();
+                existing.players.dedup();
+                false
+            }
+            None => {
+                self.by_code.insert(report.code.clone(), self.reports.len());
+                self.reports.push(report);
+                true
+            }
+        }
+    }
+}
+
+/// With a `rejects` path, malformed report lines are logged there and
+/// skipped rather than stopping the merge.
+pub fn merge(
+    datasets: &[PathBuf],
+    output_dir: &Path,
+    rejects: Option<&Path>,
+) -> Result<MergeSummary, Box<dyn std::error::Error>> {
+    let mut summary = MergeSummary { datasets: datasets.len(), ..MergeSummary::default() };
+    let mut rejects = rejects.map(Rejects::create).transpose()?;
+    let mut players = PlayerTable::default();
+    let mut reports = ReportTable::default();
+
+    for dataset in datasets {
+        println!("Reading {}", dataset.display());
+
+        let mut id_map: HashMap<usize, usize> = HashMap::new();
+        for player in input::read_player_table(&dataset.join(PLAYERS_FILE))? {
+            let new_id = players.id_for(&player.player_name);
+            if new_id != player.id {
+                summary.ids_remapped += 1;
+            }
+            id_map.insert(player.id, new_id);
+            summary.players_read += 1;
+        }
+
+        input::for_each_report(&dataset.join(REPORTS_FILE), rejects.as_mut(), |report| {
+            summary.reports_read += 1;
+            let mut merged_players: Vec<usize> = Vec::with_capacity(report.players.len());
+            for id in &report.players {
+                match id_map.get(id) {
+                    Some(&new_id) => merged_players.push(new_id),
+                    None => summary.unknown_players += 1,
+                }
+            }
+            merged_players.sort_unstable();
+            merged_players.dedup();
+            let merged = Report {
+                start_time: report.start_time,
+                end_time: report.end_time,
+                players: merged_players,
+                code: report.code.clone(),
+            };
+            if !reports.insert(merged) {
+                summary.codes_collapsed += 1;
+            }
+        })?;
+    }
+    if let Some(rejects) = rejects {
+        summary.lines_rejected = rejects.finish()?;
+    }
+
+    create_dir_all(output_dir)?;
+    let mut wtr = csv::Writer::from_path(output_dir.join(MERGED_PLAYERS_FILE))?;
+    for player in &players.players {
+        wtr.serialize(player)?;
+    }
+    wtr.flush()?;
+    summary.players_written = players.players.len();
+
+    let mut output = BufWriter::new(File::create(output_dir.join(MERGED_REPORTS_FILE))?);
+    for report in &reports.reports {
+        serde_json::to_writer(&mut output, report)?;
+        writeln!(output)?;
+    }
+    output.flush()?;
+    summary.reports_written = reports.reports.len();
+
+    Ok(summary)
+}
+
+pub fn run(datasets: &[PathBuf], output_dir: &Path, rejects: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let summary = merge(datasets, output_dir, rejects)?;
+    println!("Merged {} datasets into {}", summary.datasets, output_dir.display());
+    println!(
+        "Players: {} read, {} written, {} ids remapped",
+        summary.players_read, summary.players_written, summary.ids_remapped
+    );
+    println!(
+        "Reports: {} read, {} written, {} duplicate codes collapsed",
+        summary.reports_read, summary.reports_written, summary.codes_collapsed
+    );
+    if summary.unknown_players > 0 {
+        println!("Dropped {} player ids missing from their player table", summary.unknown_players);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+    use crate::testing::{report, temp_dir};
+
+    fn dataset(dir: &Path, players: &[(usize, &str)], reports: &[Report]) -> PathBuf {
+        create_dir_all(dir).unwrap();
+        let table: String = players.iter().map(|(id, name)| format!("{},{}\n", id, name)).collect();
+        write(dir.join(PLAYERS_FILE), format!("id,player_name\n{}", table)).unwrap();
+        let lines: String = reports.iter().map(|r| serde_json::to_string(r).unwrap() + "\n").collect();
+        write(dir.join(REPORTS_FILE), lines).unwrap();
+        dir.to_path_buf()
+    }
+
+    #[test]
+    fn merges_two_runs() {
+        let dir = temp_dir("merge-two-runs");
+        let first = dataset(
+            &dir.join("first"),
+            &[(1, "@alice"), (2, "@bob")],
+            &[report(100, vec![1, 2], "x"), report(500, vec![1, 9], "y")],
+        );
+        let mut widened = report(50, vec![1, 2], "x");
+        widened.end_time = 10_000_000;
+        let second = dataset(
+            &dir.join("second"),
+            &[(1, "@carol"), (2, "@bob"), (3, "@alice")],
+            &[widened, report(900, vec![3], "z")],
+        );
+        let output = dir.join("merged");
+
+        let summary = merge(&[first, second], &output, None).unwrap();
+        assert_eq!(summary.players_read, 5);
+        assert_eq!(summary.players_written, 3);
+        assert_eq!(summary.ids_remapped, 2);
+        assert_eq!(summary.reports_read, 4);
+        assert_eq!(summary.reports_written, 3);
+        assert_eq!(summary.codes_collapsed, 1);
+        assert_eq!(summary.unknown_players, 1);
+
+        let players = input::read_player_table(&output.join(MERGED_PLAYERS_FILE)).unwrap();
+        let names: Vec<(usize, &str)> = players.iter().map(|p| (p.id, p.player_name.as_str())).collect();
+        assert_eq!(names, [(1, "@alice"), (2, "@bob"), (3, "@carol")]);
+
+        let mut reports = Vec::new();
+        input::for_each_report(&output.join(MERGED_REPORTS_FILE), None, |r| reports.push(r.clone())).unwrap();
+        let codes: Vec<&str> = reports.iter().map(|r| r.code.as_str()).collect();
+        assert_eq!(codes, ["x", "y", "z"]);
+        assert_eq!((reports[0].start_time, reports[0].end_time), (50, 10_000_000));
+        assert_eq!(reports[0].players, [1, 2, 3]);
+        assert_eq!(reports[1].players, [1]);
+        assert_eq!(reports[2].players, [1]);
+    }
+}
@@ -0,0 +1,171 @@
+//! Adding up time spent together by every pair of players, the O(k²) loop
+//! over each report's players that dominates a build.
+//!
+//! With more than one thread, pairs are sharded by their smaller player id.
+//! Every shard sees the reports in file order and no two shards share a
+//! pair, so the counts come out exactly the same whatever the thread count.
+
+use std::thread;
+use rustc_hash::FxHashMap;
+
+/// An unordered pair of player ids packed into one word, smaller id first.
+/// There are hundreds of thousands of players, so ids fit in 32 bits.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+pub struct EdgeKey(u64);
+
+impl EdgeKey {
+    pub fn new(a: u32, b: u32) -> Self {
+        let (low, high) = if a < b { (a, b) } else { (b, a) };
+        EdgeKey((low as u64) << 32 | high as u64)
+    }
+
+    pub fn players(self) -> (u32, u32) {
+        ((self.0 >> 32) as u32, self.0 as u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EdgeStats {
+    pub duration: u64,
+    pub newman: f64,
+    pub reports: u32,
+}
+
+/// Reports are buffered and counted this many at a time, so threads are
+/// started once per batch rather than once per report.
+const BATCH: usize = 16_384;
+
+pub struct PairCounter {
+    threads: usize,
+    shards: Vec<FxHashMap<EdgeKey, EdgeStats>>,
+    /// Players of every buffered report, back to back and sorted within
+    /// each report.
+    players: Vec<u32>,
+    /// `(end of the report's players, duration, newman share)` per report.
+    reports: Vec<(usize, u64, f64)>,
+}
+
+impl PairCounter {
+    /// `threads` of 0 uses every available core.
+    pub fn new(threads: usize) -> Self {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        PairCounter {
+            threads,
+            shards: vec![FxHashMap::default(); threads],
+            players: Vec::new(),
+            reports: Vec::new(),
+        }
+    }
+
+    /// Counts one report towards every pair of `players` in it. `share` is
+    /// what the report adds to each pair's Newman weight.
+    pub fn add(&mut self, players: impl IntoIterator<Item = u32>, duration: u64, share: f64) {
+        let start = self.players.len();
+        self.players.extend(players);
+        self.players[start..].sort_unstable();
+        self.reports.push((self.players.len(), duration, share));
+        if self.reports.len() >= BATCH {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let (players, reports, threads) = (&self.players, &self.reports, self.threads);
+        if threads == 1 {
+            count_shard(&mut self.shards[0], players, reports, 0, 1);
+        } else {
+            thread::scope(|scope| {
+                for (shard, map) in self.shards.iter_mut().enumerate() {
+                    scope.spawn(move || count_shard(map, players, reports, shard, threads));
+                }
+            });
+        }
+        self.players.clear();
+        self.reports.clear();
+    }
+
+    /// Counts whatever is still buffered and joins the shards together.
+    pub fn finish(mut self) -> FxHashMap<EdgeKey, EdgeStats> {
+        self.flush();
+        let mut shards = self.shards.into_iter();
+        let mut merged = shards.next().unwrap_or_default();
+        for shard in shards {
+            merged.reserve(shard.len());
+            merged.extend(shard);
+        }
+        merged
+    }
+}
+
+fn count_shard(
+    map: &mut FxHashMap<EdgeKey, EdgeStats>,
+    players: &[u32],
+    reports: &[(usize, u64, f64)],
+    shard: usize,
+    shards: usize,
+) {
+    let mut start = 0;
+    for &(end, duration, share) in reports {
+        let group = &players[start..end];
+        for (i, &a) in group.iter().enumerate() {
+            if a as usize % shards != shard {
+                continue;
+            }
+            for &b in &group[i + 1..] {
+                let edge = map.entry(EdgeKey::new(a, b)).or_default();
+                edge.duration += duration;
+                edge.reports += 1;
+                edge.newman += share;
+            }
+        }
+        start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn count(reports: &[(Vec<u32>, u64)], threads: usize) -> FxHashMap<EdgeKey, EdgeStats> {
+        let mut counter = PairCounter::new(threads);
+        for (players, duration) in reports {
+            counter.add(players.iter().copied(), *duration, 1.0 / (players.len() as f64 - 1.0));
+        }
+        counter.finish()
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_counts() {
+        // Enough reports for a few batches, from a small pool so pairs
+        // repeat often and their Newman sums depend on the order added.
+        let mut rng = Rng::new(13);
+        let reports: Vec<(Vec<u32>, u64)> = (0..2 * BATCH + 1_000)
+            .map(|_| {
+                let size = 2 + rng.next_u64() % 10;
+                let mut players: Vec<u32> = (0..size).map(|_| (rng.next_u64() % 150) as u32).collect();
+                players.sort_unstable();
+                players.dedup();
+                players.reverse();
+                (players, rng.next_u64() % 7_200_000)
+            })
+            .filter(|(players, _)| players.len() > 1)
+            .collect();
+
+        let single = count(&reports, 1);
+        assert!(single.len() > 1_000);
+        for threads in [3, 8] {
+            let sharded = count(&reports, threads);
+            assert_eq!(sharded.len(), single.len());
+            for (key, stats) in &single {
+                let other = sharded[key];
+                assert_eq!(other.duration, stats.duration);
+                assert_eq!(other.reports, stats.reports);
+                assert_eq!(other.newman.to_bits(), stats.newman.to_bits());
+            }
+        }
+    }
+}
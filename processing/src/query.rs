@@ -0,0 +1,273 @@
+//! Questions about a built graph, asked by display name. The graph is read
+//! back from the json written for the visualisation.
+
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap, VecDeque}, fs::File, io::BufReader, path::Path};
+use atlas_model::graph::Graph;
+
+/// How many close names an unknown one suggests.
+const SUGGESTIONS: usize = 3;
+
+/// A built graph as adjacency lists, with nodes in file order.
+pub struct Network {
+    pub names: Vec<String>,
+    adjacency: Vec<Vec<(usize, f64)>>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Network {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let graph: Graph = serde_json::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|e| format!("Could not read graph {}: {}", path.display(), e))?;
+        Network::from_model(&graph)
+    }
+
+    pub fn from_model(graph: &Graph) -> Result<Self, Box<dyn std::error::Error>> {
+        let index: HashMap<&str, usize> = graph.nodes.iter().enumerate().map(|(i, n)| (n.key.as_str(), i)).collect();
+        let mut adjacency = vec![Vec::new(); graph.nodes.len()];
+        for edge in &graph.edges {
+            let (Some(&a), Some(&b)) = (index.get(edge.source.as_str()), index.get(edge.target.as_str())) else {
+                return Err(format!("Edge {} joins a node that isn't in the graph", edge.key).into());
+            };
+            adjacency[a].push((b, edge.attributes.weight));
+            adjacency[b].push((a, edge.attributes.weight));
+        }
+        let names: Vec<String> = graph.nodes.iter().map(|n| n.attributes.label.clone()).collect();
+        let mut by_name = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            by_name.entry(name.clone()).or_insert(i);
+        }
+        Ok(Network { names, adjacency, by_name })
+    }
+
+    /// The node with this display name, or an error suggesting close ones.
+    pub fn find(&self, name: &str) -> Result<usize, String> {
+        self.by_name.get(name).copied().ok_or_else(|| unknown_player(name, self.names.iter().map(String::as_str)))
+    }
+
+    /// Weight of the heaviest edge between `a` and `b`.
+    pub fn weight(&self, a: usize, b: usize) -> Option<f64> {
+        self.adjacency[a].iter().filter(|&&(n, _)| n == b).map(|&(_, w)| w).reduce(f64::max)
+    }
+
+    /// A path with the fewest hops, found breadth first.
+    pub fn fewest_hops(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut previous = vec![usize::MAX; self.names.len()];
+        previous[from] = from;
+        let mut queue = VecDeque::from([from]);
+        while let Some(v) = queue.pop_front() {
+            if v == to {
+                return Some(trace(&previous, from, to));
+            }
+            for &(w, _) in &self.adjacency[v] {
+                if previous[w] == usize::MAX {
+                    previous[w] = v;
+                    queue.push_back(w);
+                }
+            }
+        }
+        None
+    }
+
+    /// The path through the strongest ties: edges are as long as one over
+    /// their weight, and the shortest total length wins. Edges without a
+    /// positive weight are left out.
+    pub fn strongest_path(&self, from: usize, to: usize) -> Option<(Vec<usize>, f64)> {
+        let mut distance = vec![f64::INFINITY; self.names.len()];
+        let mut previous = vec![usize::MAX; self.names.len()];
+        distance[from] = 0.0;
+        previous[from] = from;
+        let mut heap = BinaryHeap::from([Visit { distance: 0.0, node: from }]);
+        while let Some(Visit { distance: d, node: v }) = heap.pop() {
+            if v == to {
+                return Some((trace(&previous, from, to), d));
+            }
+            if d > distance[v] {
+                continue;
+            }
+            for &(w, weight) in &self.adjacency[v] {
+                if weight <= 0.0 || !weight.is_finite() {
+                    continue;
+                }
+                let next = d + 1.0 / weight;
+                if next < distance[w] {
+                    distance[w] = next;
+                    previous[w] = v;
+                    heap.push(Visit { distance: next, node: w });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Heap entry for Dijkstra's algorithm, ordered so the nearest pops first.
+#[derive(PartialEq)]
+struct Visit {
+    distance: f64,
+    node: usize,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance).then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn trace(previous: &[usize], from: usize, to: usize) -> Vec<usize> {
+    let mut path = vec![to];
+    let mut v = to;
+    while v != from {
+        v = previous[v];
+        path.push(v);
+    }
+    path.reverse();
+    path
+}
+
+/// Error message for a name that isn't among `names`, with the closest
+/// ones by edit distance, ignoring case, as suggestions.
+pub fn unknown_player<'a>(name: &str, names: impl IntoIterator<Item = &'a str>) -> String {
+    let wanted = name.to_lowercase();
+    let limit = (wanted.chars().count() / 3).max(2);
+    let mut close: Vec<(usize, &str)> = names
+        .into_iter()
+        .map(|candidate| (edit_distance(&wanted, &candidate.to_lowercase()), candidate))
+        .filter(|&(d, _)| d <= limit)
+        .collect();
+    close.sort_unstable();
+    close.dedup_by_key(|&mut (_, candidate)| candidate);
+    close.truncate(SUGGESTIONS);
+
+    match close.as_slice() {
+        [] => format!("No player called {}", name),
+        close => {
+            let names: Vec<&str> = close.iter().map(|&(_, candidate)| candidate).collect();
+            format!("No player called {}. Did you mean {}?", name, names.join(", "))
+        }
+    }
+}
+
+/// Levenshtein distance over characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn print_path(network: &Network, path: &[usize]) {
+    println!("  {}", network.names[path[0]]);
+    for step in path.windows(2) {
+        let weight = network.weight(step[0], step[1]).unwrap_or(f64::NAN);
+        println!("  {:<24} weight {:.3}", network.names[step[1]], weight);
+    }
+}
+
+/// Prints the fewest-hops and strongest paths between two players.
+pub fn run_path(graph: &Path, from: &str, to: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let network = Network::load(graph)?;
+    let (a, b) = (network.find(from)?, network.find(to)?);
+
+    let Some(hops) = network.fewest_hops(a, b) else {
+        println!("{} and {} aren't connected in {}", from, to, graph.display());
+        return Ok(());
+    };
+    println!("Fewest hops: {}", hops.len() - 1);
+    print_path(&network, &hops);
+
+    if let Some((strongest, length)) = network.strongest_path(a, b) {
+        println!("Strongest ties: {} hops, length {:.3} (sum of 1 / weight)", strongest.len() - 1, length);
+        print_path(&network, &strongest);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use atlas_model::graph::{Edge, EdgeAttributes, Node, NodeAttributes};
+
+    use super::*;
+
+    /// @anna reaches @dora directly over a weak tie, or through @bert and
+    /// @carl over strong ones. @emil's only tie has no weight and @finn has
+    /// none at all.
+    fn network() -> Network {
+        let names = ["@anna", "@bert", "@carl", "@dora", "@emil", "@finn"];
+        let ties = [(0, 3, 0.1), (0, 1, 2.0), (1, 2, 2.0), (2, 3, 2.0), (0, 4, 0.0)];
+        let graph = Graph {
+            nodes: names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| Node {
+                    key: i.to_string(),
+                    attributes: NodeAttributes { label: name.to_string(), x: 0.0, y: 0.0, size: 1.0, color: String::new() },
+                })
+                .collect(),
+            edges: ties
+                .iter()
+                .enumerate()
+                .map(|(i, &(a, b, weight))| Edge {
+                    key: i.to_string(),
+                    source: a.to_string(),
+                    target: b.to_string(),
+                    attributes: EdgeAttributes { weight },
+                })
+                .collect(),
+        };
+        Network::from_model(&graph).unwrap()
+    }
+
+    #[test]
+    fn fewest_hops_and_strongest_ties_differ() {
+        let network = network();
+        let (anna, dora) = (network.find("@anna").unwrap(), network.find("@dora").unwrap());
+        assert_eq!(network.fewest_hops(anna, dora), Some(vec![0, 3]));
+        let (path, length) = network.strongest_path(anna, dora).unwrap();
+        assert_eq!(path, [0, 1, 2, 3]);
+        assert!((length - 1.5).abs() < 1e-12);
+        assert_eq!(network.fewest_hops(anna, anna), Some(vec![0]));
+    }
+
+    #[test]
+    fn disconnected_players() {
+        let network = network();
+        assert_eq!(network.fewest_hops(0, 5), None);
+        assert_eq!(network.strongest_path(0, 5), None);
+        // A tie without weight counts as a hop but not as a strong tie.
+        assert_eq!(network.fewest_hops(0, 4), Some(vec![0, 4]));
+        assert_eq!(network.strongest_path(0, 4), None);
+    }
+
+    #[test]
+    fn suggests_the_closest_names() {
+        let names = ["@annabel", "@hanna", "@anne", "@anna", "@anna", "@bert"];
+        assert_eq!(unknown_player("@ana", names), "No player called @ana. Did you mean @anna, @anne, @hanna?");
+        assert_eq!(unknown_player("@ANNE", names), "No player called @ANNE. Did you mean @anne, @anna, @hanna?");
+        assert_eq!(unknown_player("@zoe", names), "No player called @zoe");
+        assert_eq!(network().find("@dorra").unwrap_err(), "No player called @dorra. Did you mean @dora?");
+    }
+
+    #[test]
+    fn edit_distance_counts_characters() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+        assert_eq!(edit_distance("zoë", "zoe"), 1);
+    }
+}
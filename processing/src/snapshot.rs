@@ -0,0 +1,127 @@
+//! Time windows to build separate graphs for. Nodes are keyed by player id
+//! in every output, so a player has the same id in each snapshot as in the
+//! full graph.
+
+use std::collections::HashMap;
+
+use crate::{config::SnapshotConfig, dates::parse_date};
+
+/// Reports starting in `start..end` belong to the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    pub label: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Window {
+    pub fn contains(&self, ms: u64) -> bool {
+        self.start <= ms && ms < self.end
+    }
+
+    /// The label with anything that doesn't belong in a file name replaced.
+    pub fn file_stem(&self) -> String {
+        self.label
+            .chars()
+            .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .collect()
+    }
+}
+
+/// Every calendar period between the first and last report start in `span`,
+/// followed by the configured date ranges.
+pub fn windows(config: &SnapshotConfig, span: Option<(u64, u64)>) -> Result<Vec<Window>, String> {
+    let mut windows = Vec::new();
+
+    if let (Some(period), Some((first, last))) = (config.period, span) {
+        let mut start = period.start_of(first);
+        while start <= last {
+            let end = period.next(start);
+            windows.push(Window { label: period.label(start), start, end });
+            start = end;
+        }
+    }
+
+    for window in &config.windows {
+        let start = parse_date(&window.start)?;
+        let end = parse_date(&window.end)?;
+        if end <= start {
+            return Err(format!("Snapshot window {:?} ends before it starts", window.name));
+        }
+        windows.push(Window { label: window.name.clone(), start, end });
+    }
+
+    let mut stems: HashMap<String, &str> = HashMap::new();
+    for window in &windows {
+        if let Some(other) = stems.insert(window.file_stem(), &window.label) {
+            return Err(format!(
+                "Snapshot windows {:?} and {:?} would both be written to {}",
+                other,
+                window.label,
+                window.file_stem()
+            ));
+        }
+    }
+
+    Ok(windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::WindowConfig, dates::{Period, millis_of}};
+
+    fn named(name: &str, start: &str, end: &str) -> WindowConfig {
+        WindowConfig { name: name.to_string(), start: start.to_string(), end: end.to_string() }
+    }
+
+    #[test]
+    fn periods_cover_the_span() {
+        let config = SnapshotConfig { period: Some(Period::Quarter), ..SnapshotConfig::default() };
+        let span = (millis_of(2022, 11, 30), millis_of(2023, 4, 1));
+        let windows = windows(&config, Some(span)).unwrap();
+        let labels: Vec<&str> = windows.iter().map(|w| w.label.as_str()).collect();
+        assert_eq!(labels, ["2022-Q4", "2023-Q1", "2023-Q2"]);
+        assert_eq!(windows[0].start, millis_of(2022, 10, 1));
+        assert_eq!(windows[0].end, windows[1].start);
+        assert!(windows[2].contains(span.1));
+        assert!(!windows[2].contains(windows[2].end));
+        assert!(super::windows(&config, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn named_windows_follow_the_periods() {
+        let config = SnapshotConfig {
+            period: Some(Period::Year),
+            windows: vec![named("Gold Road", "2024-06-03", "2024-10-28")],
+            ..SnapshotConfig::default()
+        };
+        let windows = windows(&config, Some((millis_of(2024, 1, 1), millis_of(2024, 1, 1)))).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[1].file_stem(), "Gold_Road");
+        assert_eq!(windows[1].end, millis_of(2024, 10, 28));
+    }
+
+    #[test]
+    fn rejects_backwards_and_clashing_windows() {
+        let backwards = SnapshotConfig {
+            windows: vec![named("late", "2024-02-01", "2024-01-01")],
+            ..SnapshotConfig::default()
+        };
+        assert!(windows(&backwards, None).unwrap_err().contains("ends before it starts"));
+
+        let clash = SnapshotConfig {
+            period: Some(Period::Year),
+            windows: vec![named("2023", "2023-03-01", "2023-06-01")],
+            ..SnapshotConfig::default()
+        };
+        let span = Some((millis_of(2023, 5, 1), millis_of(2023, 5, 1)));
+        assert!(windows(&clash, span).unwrap_err().contains("would both be written to 2023"));
+
+        let stems = SnapshotConfig {
+            windows: vec![named("a/b", "2023-01-01", "2023-02-01"), named("a b", "2023-01-01", "2023-02-01")],
+            ..SnapshotConfig::default()
+        };
+        assert!(windows(&stems, None).is_err());
+    }
+}
@@ -0,0 +1,367 @@
+//! Recurring rosters ("statics"): groups of players who keep raiding
+//! together. Candidates are the maximal cliques of the graph linking players
+//! who shared at least `min_runs` reports. Each is then checked against the
+//! reports, counting a run whenever `min_share` of its members turn up
+//! together, since a static rarely fields its full roster every week.
+
+use std::{fs::File, io::{BufWriter, Write}, path::Path};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+
+use crate::{
+    build::{self, Reports},
+    config::Config,
+    dates::{MS_PER_HOUR, format_date},
+    input,
+};
+
+/// Clique enumeration stops here, as a dense pool of players can have a
+/// great many.
+const MAX_CLIQUES: usize = 100_000;
+/// A group sharing more than this part of its members with a better one is
+/// the same static with a different bench.
+const MAX_SHARED: f64 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct StaticOptions<'a> {
+    /// Smallest group reported.
+    pub min_size: usize,
+    /// Runs a group needs, and reports every pair in it must share.
+    pub min_runs: usize,
+    /// Part of a group that must be in a report for it to count as a run.
+    pub min_share: f64,
+    pub top: usize,
+    pub output: &'a Path,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Static {
+    pub members: Vec<String>,
+    pub runs: usize,
+    pub hours: f64,
+    pub first_run: String,
+    pub last_run: String,
+}
+
+/// A candidate group, as indices into the clique graph's nodes.
+struct Group {
+    members: Vec<usize>,
+    runs: usize,
+    duration: u64,
+    first: u64,
+    last: u64,
+}
+
+pub fn run(config: &Config, options: &StaticOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let id_to_name = input::read_players(&config.input.players)?;
+    let reports = Reports::open(config)?;
+    let appearances = build::count_appearances(&reports, config, None)?;
+
+    let regulars: FxHashSet<u32> = appearances
+        .players
+        .iter()
+        .filter(|(_, stats)| stats.appearances >= options.min_runs)
+        .map(|(&id, _)| id)
+        .collect();
+    let pairs = build::accumulate_edges(&reports, config, None, &regulars, None)?;
+
+    let mut ids: Vec<u32> = regulars.into_iter().collect();
+    ids.sort_unstable();
+    let index_of: FxHashMap<u32, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut adjacency = vec![Vec::new(); ids.len()];
+    for (key, stats) in &pairs {
+        if (stats.reports as usize) < options.min_runs {
+            continue;
+        }
+        let (a, b) = key.players();
+        let (a, b) = (index_of[&a], index_of[&b]);
+        adjacency[a].push(b);
+        adjacency[b].push(a);
+    }
+    adjacency.iter_mut().for_each(|a| a.sort_unstable());
+    drop(pairs);
+
+    let cliques = maximal_cliques(&adjacency, options.min_size);
+    if cliques.len() >= MAX_CLIQUES {
+        println!("Stopped after {} candidate groups; a higher --min-runs narrows the search", MAX_CLIQUES);
+    }
+    println!("Checking {} candidate groups against the reports", cliques.len());
+
+    let mut groups: Vec<Group> = cliques
+        .into_iter()
+        .map(|members| Group { members, runs: 0, duration: 0, first: u64::MAX, last: 0 })
+        .collect();
+    let needed: Vec<usize> = groups
+        .iter()
+        .map(|g| ((g.members.len() as f64 * options.min_share).ceil() as usize).max(2))
+        .collect();
+    let mut groups_of: Vec<Vec<usize>> = vec![Vec::new(); ids.len()];
+    for (g, group) in groups.iter().enumerate() {
+        for &member in &group.members {
+            groups_of[member].push(g);
+        }
+    }
+
+    let mut present: FxHashMap<usize, usize> = FxHashMap::default();
+    reports.for_each_valid(config, None, |_, report, duration| {
+        present.clear();
+        for id in &report.players {
+            if let Some(&node) = index_of.get(&(*id as u32)) {
+                for &g in &groups_of[node] {
+                    *present.entry(g).or_default() += 1;
+                }
+            }
+        }
+        for (&g, &count) in &present {
+            if count >= needed[g] {
+                let group = &mut groups[g];
+                group.runs += 1;
+                group.duration += duration;
+                group.first = group.first.min(report.start_time);
+                group.last = group.last.max(report.start_time);
+            }
+        }
+    })?;
+
+    groups.retain(|g| g.runs >= options.min_runs);
+    groups.sort_by(|a, b| {
+        b.runs
+            .cmp(&a.runs)
+            .then(b.members.len().cmp(&a.members.len()))
+            .then(b.duration.cmp(&a.duration))
+            .then(a.members.cmp(&b.members))
+    });
+    let mut kept: Vec<Group> = Vec::new();
+    for group in groups {
+        if kept.len() == options.top {
+            break;
+        }
+        if kept.iter().all(|k| shared(&k.members, &group.members) <= MAX_SHARED) {
+            kept.push(group);
+        }
+    }
+
+    let statics: Vec<Static> = kept
+        .iter()
+        .map(|group| {
+            let name = |&m: &usize| id_to_name.get(&(ids[m] as usize)).cloned().unwrap_or_else(|| ids[m].to_string());
+            Static {
+                members: group.members.iter().map(name).collect(),
+                runs: group.runs,
+                hours: group.duration as f64 / MS_PER_HOUR as f64,
+                first_run: format_date(group.first),
+                last_run: format_date(group.last),
+            }
+        })
+        .collect();
+
+    print_statics(&statics, options);
+    let mut output = BufWriter::new(File::create(options.output)?);
+    serde_json::to_writer_pretty(&mut output, &statics)?;
+    output.flush()?;
+    println!("Wrote {}", options.output.display());
+    Ok(())
+}
+
+fn print_statics(statics: &[Static], options: &StaticOptions) {
+    println!("Found {} statics of {} or more players", statics.len(), options.min_size);
+    for (rank, s) in statics.iter().enumerate() {
+        println!(
+            "{:>3}. {} players, {} runs, {:.1} hours, {} to {}",
+            rank + 1,
+            s.members.len(),
+            s.runs,
+            s.hours,
+            s.first_run,
+            s.last_run
+        );
+        println!("     {}", s.members.join(", "));
+    }
+}
+
+/// Shared members over the size of the smaller group. Both are sorted.
+fn shared(a: &[usize], b: &[usize]) -> f64 {
+    let common = a.iter().filter(|m| b.binary_search(m).is_ok()).count();
+    common as f64 / a.len().min(b.len()) as f64
+}
+
+/// Maximal cliques with at least `min_size` nodes, by Bron–Kerbosch with
+/// pivoting. `adjacency` lists are sorted, and so is every clique.
+fn maximal_cliques(adjacency: &[Vec<usize>], min_size: usize) -> Vec<Vec<usize>> {
+    let mut cliques = Vec::new();
+    let mut clique = Vec::new();
+    for v in 0..adjacency.len() {
+        if adjacency[v].len() + 1 < min_size {
+            continue;
+        }
+        // Each clique is found from its lowest node only.
+        let (earlier, later): (Vec<usize>, Vec<usize>) = adjacency[v].iter().partition(|&&w| w < v);
+        clique.push(v);
+        expand(adjacency, &mut clique, later, earlier, min_size, &mut cliques);
+        clique.pop();
+        if cliques.len() >= MAX_CLIQUES {
+            break;
+        }
+    }
+    cliques
+}
+
+fn expand(
+    adjacency: &[Vec<usize>],
+    clique: &mut Vec<usize>,
+    mut candidates: Vec<usize>,
+    mut excluded: Vec<usize>,
+    min_size: usize,
+    cliques: &mut Vec<Vec<usize>>,
+) {
+    if candidates.is_empty() {
+        if excluded.is_empty() && clique.len() >= min_size {
+            let mut found = clique.clone();
+            found.sort_unstable();
+            cliques.push(found);
+        }
+        return;
+    }
+    if clique.len() + candidates.len() < min_size {
+        return;
+    }
+
+    let pivot = candidates
+        .iter()
+        .chain(&excluded)
+        .copied()
+        .max_by_key(|&u| (intersect(&candidates, &adjacency[u]).len(), std::cmp::Reverse(u)))
+        .unwrap_or(candidates[0]);
+    let branches: Vec<usize> = candidates.iter().copied().filter(|v| adjacency[pivot].binary_search(v).is_err()).collect();
+    for v in branches {
+        clique.push(v);
+        expand(
+            adjacency,
+            clique,
+            intersect(&candidates, &adjacency[v]),
+            intersect(&excluded, &adjacency[v]),
+            min_size,
+            cliques,
+        );
+        clique.pop();
+        if cliques.len() >= MAX_CLIQUES {
+            return;
+        }
+        candidates.retain(|&w| w != v);
+        let at = excluded.partition_point(|&w| w < v);
+        excluded.insert(at, v);
+    }
+}
+
+/// Items in both sorted lists.
+fn intersect(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let (mut i, mut j) = (0, 0);
+    let mut both = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                both.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    both
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, write};
+
+    use super::*;
+    use crate::testing::{report, temp_dir};
+
+    /// Lists of sorted neighbours from undirected edges.
+    fn adjacency(n: usize, edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::new(); n];
+        for &(a, b) in edges {
+            adjacency[a].push(b);
+            adjacency[b].push(a);
+        }
+        adjacency.iter_mut().for_each(|a| a.sort_unstable());
+        adjacency
+    }
+
+    #[test]
+    fn finds_each_maximal_clique_once() {
+        // 0-3 are all linked, and 4 and 5 each link to all of them but not
+        // to each other. 6-8 form a triangle with a tail to 9.
+        let mut edges = vec![(6, 7), (7, 8), (6, 8), (8, 9)];
+        for a in 0..4 {
+            edges.extend((a + 1..4).map(|b| (a, b)));
+            edges.extend([(a, 4), (a, 5)]);
+        }
+        let adjacency = adjacency(10, &edges);
+
+        let mut cliques = maximal_cliques(&adjacency, 2);
+        cliques.sort();
+        assert_eq!(cliques, [vec![0, 1, 2, 3, 4], vec![0, 1, 2, 3, 5], vec![6, 7, 8], vec![8, 9]]);
+        assert_eq!(maximal_cliques(&adjacency, 3).len(), 3);
+        assert_eq!(maximal_cliques(&adjacency, 5).len(), 2);
+        assert!(maximal_cliques(&adjacency, 6).is_empty());
+    }
+
+    #[test]
+    fn stops_at_max_cliques() {
+        // 11 triples, each linked to every node outside it, have 3^11
+        // maximal cliques of one node from each triple.
+        let edges: Vec<(usize, usize)> = (0..33)
+            .flat_map(|a| (a + 1..33).filter(move |b| b / 3 != a / 3).map(move |b| (a, b)))
+            .collect();
+        let cliques = maximal_cliques(&adjacency(33, &edges), 11);
+        assert_eq!(cliques.len(), MAX_CLIQUES);
+        assert!(cliques.iter().all(|c| c.len() == 11));
+    }
+
+    #[test]
+    fn counts_runs_and_drops_the_bench_variant() {
+        // 1-4 always raid together, with 5 for three runs and 6 for three
+        // more: two candidate groups that are the same static.
+        let dir = temp_dir("statics-bench");
+        let rosters: [&[usize]; 7] =
+            [&[1, 2, 3, 4, 5], &[1, 2, 3, 4, 5], &[1, 2, 3, 4, 5], &[1, 2, 3, 4, 6], &[1, 2, 3, 4, 6], &[1, 2, 3, 4, 6], &[7, 8]];
+        let lines: String = rosters
+            .iter()
+            .enumerate()
+            .map(|(i, r)| serde_json::to_string(&report(i as u64 * MS_PER_HOUR, r.iter().copied(), &i.to_string())).unwrap() + "\n")
+            .collect();
+        let players: String = (1..=8).map(|id| format!("{},@p{}\n", id, id)).collect();
+        let mut config = Config::default();
+        config.input.players = dir.join("players.csv");
+        config.input.reports = dir.join("reports.json");
+        config.input.cache = false;
+        write(&config.input.players, format!("id,player_name\n{}", players)).unwrap();
+        write(&config.input.reports, lines).unwrap();
+
+        let output = dir.join("statics.json");
+        let statics = |min_runs, min_share| {
+            let options = StaticOptions { min_size: 4, min_runs, min_share, top: 10, output: &output };
+            run(&config, &options).unwrap();
+            let statics: serde_json::Value = serde_json::from_str(&read_to_string(&output).unwrap()).unwrap();
+            statics
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| (s["members"].as_array().unwrap().len(), s["runs"].as_u64().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        // Four of five players make a run, so both variants have six.
+        assert_eq!(statics(3, 0.8), [(5, 6)]);
+        assert_eq!(statics(3, 1.0), [(5, 3)]);
+        // 5 and 6 share only three reports with the rest, leaving the core.
+        assert_eq!(statics(4, 1.0), [(4, 6)]);
+        assert!(statics(7, 0.5).is_empty());
+
+        let options = StaticOptions { min_size: 4, min_runs: 3, min_share: 0.8, top: 10, output: &output };
+        run(&config, &options).unwrap();
+        let text = read_to_string(&output).unwrap();
+        assert!(text.contains("@p5") && !text.contains("@p6"), "{}", text);
+    }
+}
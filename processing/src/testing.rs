@@ -0,0 +1,19 @@
+//! Fixtures shared by the unit tests.
+
+use std::{fs::{create_dir_all, remove_dir_all}, path::PathBuf};
+use atlas_model::Report;
+
+use crate::dates::MS_PER_HOUR;
+
+/// A one hour report starting at `start_time`.
+pub fn report(start_time: u64, players: impl IntoIterator<Item = usize>, code: &str) -> Report {
+    Report { start_time, end_time: start_time + MS_PER_HOUR, players: players.into_iter().collect(), code: code.to_string() }
+}
+
+/// An empty directory for one test, under the system temp directory.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("atlas-{}-{}", name, std::process::id()));
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,195 @@
+//! Sanity checks on a player table and report file, to catch bad data
+//! before spending hours on a build.
+
+use std::{collections::{HashMap, hash_map::Entry}, path::Path};
+use rustc_hash::FxHashSet;
+
+use crate::{dates::MS_PER_HOUR, input};
+
+/// Examples kept per problem.
+const EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub description: &'static str,
+    pub count: usize,
+    pub examples: Vec<String>,
+}
+
+impl Check {
+    fn new(description: &'static str) -> Self {
+        Check { description, count: 0, examples: Vec::new() }
+    }
+
+    fn record(&mut self, example: impl Into<String>) {
+        self.count += 1;
+        if self.examples.len() < EXAMPLES {
+            self.examples.push(example.into());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub players: usize,
+    pub reports: usize,
+    pub malformed_lines: Check,
+    pub end_before_start: Check,
+    pub zero_duration: Check,
+    pub absurd_duration: Check,
+    pub empty_players: Check,
+    pub unknown_players: Check,
+    pub duplicate_codes: Check,
+    pub duplicate_names: Check,
+    pub duplicate_ids: Check,
+}
+
+impl Validation {
+    pub fn checks(&self) -> [&Check; 9] {
+        [
+            &self.malformed_lines,
+            &self.end_before_start,
+            &self.zero_duration,
+            &self.absurd_duration,
+            &self.empty_players,
+            &self.unknown_players,
+            &self.duplicate_codes,
+            &self.duplicate_names,
+            &self.duplicate_ids,
+        ]
+    }
+
+    pub fn problems(&self) -> usize {
+        self.checks().iter().map(|c| c.count).sum()
+    }
+}
+
+/// Reports longer than `max_duration_ms` count as absurd.
+pub fn validate(players: &Path, reports: &Path, max_duration_ms: u64) -> Result<Validation, Box<dyn std::error::Error>> {
+    let mut validation = Validation {
+        players: 0,
+        reports: 0,
+        malformed_lines: Check::new("report lines that aren't a valid report"),
+        end_before_start: Check::new("reports ending before they start"),
+        zero_duration: Check::new("reports with zero duration"),
+        absurd_duration: Check::new("reports longer than the duration limit"),
+        empty_players: Check::new("reports with no players"),
+        unknown_players: Check::new("reports with ids missing from the player table"),
+        duplicate_codes: Check::new("reports with a code seen earlier"),
+        duplicate_names: Check::new("player names listed under more than one id"),
+        duplicate_ids: Check::new("player ids listed more than once"),
+    };
+
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut ids: FxHashSet<usize> = FxHashSet::default();
+    for player in input::read_player_table(players)? {
+        validation.players += 1;
+        if !ids.insert(player.id) {
+            validation.duplicate_ids.record(player.id.to_string());
+        }
+        match names.entry(player.player_name) {
+            Entry::Occupied(e) if *e.get() != player.id => {
+                validation.duplicate_names.record(format!("{} ({} and {})", e.key(), e.get(), player.id));
+            }
+            Entry::Occupied(_) => {}
+            Entry::Vacant(e) => {
+                e.insert(player.id);
+            }
+        }
+    }
+
+    let mut codes: FxHashSet<String> = FxHashSet::default();
+    let mut malformed = Check::new(validation.malformed_lines.description);
+    let on_error = |line, e: serde_json::Error| {
+        malformed.record(format!("line {}: {}", line, e));
+        Ok(())
+    };
+    input::for_each_report_with(reports, on_error, |report| {
+        validation.reports += 1;
+        let code = report.code.as_str();
+        match report.end_time.checked_sub(report.start_time) {
+            None => validation.end_before_start.record(code),
+            Some(0) => validation.zero_duration.record(code),
+            Some(duration) if duration > max_duration_ms => validation.absurd_duration.record(code),
+            Some(_) => {}
+        }
+        if report.players.is_empty() {
+            validation.empty_players.record(code);
+        }
+        if report.players.iter().any(|id| !ids.contains(id)) {
+            validation.unknown_players.record(code);
+        }
+        if !codes.insert(report.code.clone()) {
+            validation.duplicate_codes.record(code);
+        }
+    })?;
+    validation.malformed_lines = malformed;
+
+    Ok(validation)
+}
+
+pub fn run(players: &Path, reports: &Path, max_duration_hours: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let validation = validate(players, reports, max_duration_hours * MS_PER_HOUR)?;
+    println!("Checked {} players and {} reports", validation.players, validation.reports);
+    for check in validation.checks() {
+        if check.count == 0 {
+            println!("  {}: none", check.description);
+        } else {
+            println!("  {}: {} (e.g. {})", check.description, check.count, check.examples.join(", "));
+        }
+    }
+
+    match validation.problems() {
+        0 => {
+            println!("No problems found");
+            Ok(())
+        }
+        n => Err(format!("Found {} problems", n).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn validate_lines(name: &str, players: &str, reports: &[&str]) -> Validation {
+        let dir = temp_dir(name);
+        write(dir.join("players.csv"), players).unwrap();
+        write(dir.join("reports.json"), reports.join("\n")).unwrap();
+        validate(&dir.join("players.csv"), &dir.join("reports.json"), 24 * MS_PER_HOUR).unwrap()
+    }
+
+    #[test]
+    fn counts_one_row_per_check() {
+        let players = "id,player_name\n1,@a\n2,@b\n2,@c\n3,@a\n";
+        let reports = [
+            r#"{"startTime":0,"endTime":3600000,"players":[1,2],"code":"fine"}"#,
+            r#"{"startTime":0,"endTime":"#,
+            r#"{"startTime":100,"endTime":50,"players":[1],"code":"backwards"}"#,
+            r#"{"startTime":100,"endTime":100,"players":[1],"code":"instant"}"#,
+            r#"{"startTime":0,"endTime":90000000,"players":[1],"code":"endless"}"#,
+            r#"{"startTime":0,"endTime":3600000,"players":[],"code":"empty"}"#,
+            r#"{"startTime":0,"endTime":3600000,"players":[1,99],"code":"stranger"}"#,
+            r#"{"startTime":0,"endTime":3600000,"players":[2],"code":"fine"}"#,
+        ];
+        let validation = validate_lines("validate-checks", players, &reports);
+        assert_eq!((validation.players, validation.reports), (4, 7));
+        for check in validation.checks() {
+            assert_eq!(check.count, 1, "{}", check.description);
+        }
+        assert_eq!(validation.duplicate_names.examples, ["@a (1 and 3)"]);
+        assert_eq!(validation.problems(), 9);
+    }
+
+    #[test]
+    fn backwards_report_can_also_be_empty() {
+        let reports = [r#"{"startTime":100,"endTime":50,"players":[],"code":"x"}"#];
+        let validation = validate_lines("validate-both", "id,player_name\n1,@a\n", &reports);
+        assert_eq!(validation.end_before_start.count, 1);
+        assert_eq!(validation.empty_players.count, 1);
+        assert_eq!(validation.problems(), 2);
+    }
+}
@@ -0,0 +1,245 @@
+//! Ways of weighing an edge from how often and how long two players raided
+//! together. The weight decides which edges a player keeps, and is what the
+//! layout and community detection see.
+
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::FilterConfig, dates::{MS_PER_DAY, parse_date}};
+
+/// Everything known about a pair of players when weighing their edge.
+/// Counts are over the reports the graph is being built from.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PairStats {
+    /// Reports both players appear in.
+    pub reports: usize,
+    /// Time spent in those reports, capped per report and decayed with age
+    /// when a half-life is set.
+    pub duration_ms: u64,
+    /// Sum of 1/(n−1) over those reports, n being the report's player count,
+    /// decayed like `duration_ms`.
+    pub newman: f64,
+    /// Reports each player appears in.
+    pub appearances: (usize, usize),
+    pub total_reports: usize,
+}
+
+/// A definition of "raids together". Weights of zero or less drop the edge.
+pub trait Weighting {
+    fn weight(&self, pair: &PairStats) -> f64;
+}
+
+/// Number of shared reports.
+pub struct Count;
+
+/// log10 of the shared time, the weighting the atlas has always used.
+pub struct Duration;
+
+/// Newman's collaboration weighting: each report splits one unit between
+/// every other player in it, so big groups count for less per pair.
+pub struct Newman;
+
+/// Shared reports over reports either player was in.
+pub struct Jaccard;
+
+/// Shared reports over the reports of the less active player.
+pub struct Overlap;
+
+/// Pointwise mutual information: how much more often the pair raid together
+/// than chance would suggest. Pairs that meet less than chance are dropped.
+pub struct Pmi;
+
+impl Weighting for Count {
+    fn weight(&self, pair: &PairStats) -> f64 {
+        pair.reports as f64
+    }
+}
+
+impl Weighting for Duration {
+    fn weight(&self, pair: &PairStats) -> f64 {
+        (pair.duration_ms as f64).log10()
+    }
+}
+
+impl Weighting for Newman {
+    fn weight(&self, pair: &PairStats) -> f64 {
+        pair.newman
+    }
+}
+
+impl Weighting for Jaccard {
+    fn weight(&self, pair: &PairStats) -> f64 {
+        let union = pair.appearances.0 + pair.appearances.1 - pair.reports;
+        pair.reports as f64 / union.max(1) as f64
+    }
+}
+
+impl Weighting for Overlap {
+    fn weight(&self, pair: &PairStats) -> f64 {
+        pair.reports as f64 / pair.appearances.0.min(pair.appearances.1).max(1) as f64
+    }
+}
+
+impl Weighting for Pmi {
+    fn weight(&self, pair: &PairStats) -> f64 {
+        let n = pair.total_reports as f64;
+        let expected = pair.appearances.0 as f64 * pair.appearances.1 as f64 / n;
+        (pair.reports as f64 / expected).ln()
+    }
+}
+
+/// Exponential decay of each report's time and Newman share with its age,
+/// so the graph shows who people raid with now rather than over their whole
+/// history. Report counts aren't decayed, so only the `duration` and
+/// `newman` weightings can be. Reports after the reference date count fully.
+#[derive(Debug, Clone, Copy)]
+pub struct Decay {
+    reference: u64,
+    half_life_ms: f64,
+}
+
+impl Decay {
+    /// The decay `filters` ask for, if any, measured back from `latest`
+    /// unless they name a reference date.
+    pub fn from_filters(filters: &FilterConfig, latest: Option<u64>) -> Result<Option<Self>, String> {
+        let Some(days) = filters.half_life_days else {
+            return Ok(None);
+        };
+        if !(days > 0.0 && days.is_finite()) {
+            return Err(format!("half_life_days must be a positive number of days, not {}", days));
+        }
+        if !matches!(filters.weighting, Scheme::Duration | Scheme::Newman) {
+            return Err("half_life_days needs the duration or newman weighting, as report counts aren't decayed".to_string());
+        }
+        let reference = match (&filters.reference_date, latest) {
+            (Some(date), _) => parse_date(date)?,
+            (None, Some(latest)) => latest,
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(Decay { reference, half_life_ms: days * MS_PER_DAY as f64 }))
+    }
+
+    pub fn reference(&self) -> u64 {
+        self.reference
+    }
+
+    /// What a report starting at `start` is multiplied by.
+    pub fn factor(&self, start: u64) -> f64 {
+        0.5f64.powf(self.reference.saturating_sub(start) as f64 / self.half_life_ms)
+    }
+}
+
+/// The weighting to use, as named in profiles and on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    Count,
+    Duration,
+    Newman,
+    Jaccard,
+    Overlap,
+    Pmi,
+}
+
+impl Scheme {
+    pub fn weighting(self) -> &'static dyn Weighting {
+        match self {
+            Scheme::Count => &Count,
+            Scheme::Duration => &Duration,
+            Scheme::Newman => &Newman,
+            Scheme::Jaccard => &Jaccard,
+            Scheme::Overlap => &Overlap,
+            Scheme::Pmi => &Pmi,
+        }
+    }
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "count" => Ok(Scheme::Count),
+            "duration" => Ok(Scheme::Duration),
+            "newman" => Ok(Scheme::Newman),
+            "jaccard" => Ok(Scheme::Jaccard),
+            "overlap" => Ok(Scheme::Overlap),
+            "pmi" => Ok(Scheme::Pmi),
+            _ => Err(format!(
+                "Unknown weighting {:?}, expected count, duration, newman, jaccard, overlap or pmi",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_a_known_pair() {
+        // 4 shared reports out of 10 and 5, in 100 reports overall.
+        let pair = PairStats { reports: 4, duration_ms: 10_000, newman: 1.5, appearances: (10, 5), total_reports: 100 };
+        let table = [
+            (Scheme::Count, 4.0),
+            (Scheme::Duration, 4.0),
+            (Scheme::Newman, 1.5),
+            (Scheme::Jaccard, 4.0 / 11.0),
+            (Scheme::Overlap, 4.0 / 5.0),
+            (Scheme::Pmi, 8f64.ln()),
+        ];
+        for (scheme, expected) in table {
+            let weight = scheme.weighting().weight(&pair);
+            assert!((weight - expected).abs() < 1e-12, "{:?} gave {}, not {}", scheme, weight, expected);
+        }
+    }
+
+    fn decay(weighting: Scheme, half_life_days: f64) -> Result<Option<Decay>, String> {
+        let filters = FilterConfig { weighting, half_life_days: Some(half_life_days), ..FilterConfig::default() };
+        Decay::from_filters(&filters, Some(100 * MS_PER_DAY))
+    }
+
+    #[test]
+    fn decay_halves_every_half_life() {
+        let decay = decay(Scheme::Duration, 30.0).unwrap().unwrap();
+        assert_eq!(decay.reference(), 100 * MS_PER_DAY);
+        assert_eq!(decay.factor(70 * MS_PER_DAY), 0.5);
+        assert_eq!(decay.factor(40 * MS_PER_DAY), 0.25);
+        assert_eq!(decay.factor(100 * MS_PER_DAY), 1.0);
+        assert_eq!(decay.factor(130 * MS_PER_DAY), 1.0);
+
+        let filters = FilterConfig {
+            half_life_days: Some(30.0),
+            reference_date: Some("1970-01-31".to_string()),
+            ..FilterConfig::default()
+        };
+        assert_eq!(Decay::from_filters(&filters, None).unwrap().unwrap().factor(0), 0.5);
+        let filters = FilterConfig { reference_date: None, ..filters };
+        assert!(Decay::from_filters(&filters, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn decay_needs_a_decayable_weighting() {
+        assert!(decay(Scheme::Newman, 30.0).unwrap().is_some());
+        for scheme in [Scheme::Count, Scheme::Jaccard, Scheme::Overlap, Scheme::Pmi] {
+            assert!(decay(scheme, 30.0).unwrap_err().contains("duration or newman"), "{:?}", scheme);
+        }
+    }
+
+    #[test]
+    fn decay_needs_a_positive_half_life() {
+        for days in [0.0, -7.0, f64::NAN, f64::INFINITY] {
+            assert!(decay(Scheme::Duration, days).unwrap_err().starts_with("half_life_days"), "{}", days);
+        }
+    }
+
+    #[test]
+    fn pmi_drops_pairs_below_chance() {
+        // Chance would put them together in 20 of the 100 reports.
+        let pair = PairStats { reports: 10, appearances: (50, 40), total_reports: 100, ..PairStats::default() };
+        assert!(Pmi.weight(&pair) < 0.0);
+        let pair = PairStats { reports: 20, ..pair };
+        assert!(Pmi.weight(&pair).abs() < 1e-12);
+    }
+}
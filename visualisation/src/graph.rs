@@ -1,45 +1,14 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use serde::Deserialize;
+use atlas_model::graph::Graph;
 use stylist::css;
 use wasm_bindgen::{prelude::Closure, JsCast};
 use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement, HtmlInputElement};
 use yew::{function_component, html, use_effect_with, use_memo, use_mut_ref, use_node_ref, use_state, Callback, Html, InputEvent, KeyboardEvent, MouseEvent, Properties, TargetCast, TouchEvent, UseStateHandle, WheelEvent};
 use yew_icons::{Icon, IconId};
 
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct Graph {
-    pub nodes: Vec<Node>,
-    pub edges: Vec<Edge>,
-}
-
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct Node {
-    pub key: String,
-    pub attributes: NodeAttributes,
-}
-
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct NodeAttributes {
-    pub label: String,
-    pub x: f64,
-    pub y: f64,
-    pub size: f32,
-    pub color: String,
-}
-
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct Edge {
-    pub key: String,
-    pub source: String,
-    pub target: String,
-    pub attributes: EdgeAttributes,
-}
-
-#[derive(Debug, Deserialize, PartialEq)]
-pub struct EdgeAttributes {
-    pub weight: f64,
-}
+/// Pending animation frame callback, shared with the closure it schedules.
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
 
 #[derive(Properties, PartialEq)]
 pub struct CanvasGraphProps {
@@ -69,7 +38,7 @@ fn draw_edges_in_batches(context: &CanvasRenderingContext2d, graph: Rc<Graph>, d
     let batch_size = 120;
     let total_edges = graph.edges.len();
 
-    let closure: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
+    let closure: FrameCallback = Rc::new(RefCell::new(None));
     {
         let closure_inner = closure.clone();
         *closure_inner.borrow_mut() = Some(Closure::wrap(Box::new({
@@ -109,7 +78,7 @@ fn draw_edges_in_batches(context: &CanvasRenderingContext2d, graph: Rc<Graph>, d
                     let dy = ty - sy;
                     let len = (dx*dx + dy*dy).sqrt();
                     let (nx, ny) = if len != 0.0 { (dy/len, -dx/len) } else { (0.0,0.0) };
-                    let curve_offset = (len/5.0).min(40.0).max(10.0);
+                    let curve_offset = (len/5.0).clamp(10.0, 40.0);
                     let cx = (sx+tx)/2.0 + nx * curve_offset;
                     let cy = (sy+ty)/2.0 + ny * curve_offset;
 
@@ -171,7 +140,7 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
 
     let selected_node = use_state(|| None::<usize>);
     let search_open = use_state(|| false);
-    let search_query = use_state(|| String::new());
+    let search_query = use_state(String::new);
 
     let scale_ref = use_mut_ref(|| *scale);
     let offset_x_ref = use_mut_ref(|| *offset_x);
@@ -202,7 +171,7 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
     
     let graph = graph_rc.clone();
 
-    let matches_ref = use_mut_ref(|| Vec::<(usize, String)>::new());
+    let matches_ref = use_mut_ref(Vec::<(usize, String)>::new);
     let matches = {
         let query = (*search_query).to_lowercase().trim_matches('@').to_owned();
         if query.len() >= 3 {
@@ -328,7 +297,7 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
                 context.scale(scale, scale).unwrap();
 
                 *drawn_edges.borrow_mut() = 0;
-                draw_edges_in_batches(&context, graph, drawn_edges, scale.clone(), selected, raf_handle.clone(), timeout_handle.clone());
+                draw_edges_in_batches(&context, graph, drawn_edges, scale, selected, raf_handle.clone(), timeout_handle.clone());
             }
             move || {
                 if let Some(to) = timeout_handle.borrow_mut().take() {
@@ -347,8 +316,8 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
         let scale = *scale;
         let offset_x = *offset_x;
         let offset_y = *offset_y;
-        let selected = (*selected_node).clone();
-        use_effect_with((graph.clone(), width, height, scale, offset_x, offset_y, selected.clone()), move |_| {
+        let selected = *selected_node;
+        use_effect_with((graph.clone(), width, height, scale, offset_x, offset_y, selected), move |_| {
             if let Some(canvas) = canvas_ref.cast::<HtmlCanvasElement>() {
                 let context = canvas
                     .get_context("2d").unwrap().unwrap()
@@ -402,7 +371,7 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
                                 let dy = ty - sy;
                                 let dist = (dx*dx + dy*dy).sqrt();
                                 let (nx, ny) = if dist != 0.0 { (dy/dist, -dx/dist) } else { (0.0, 0.0) };
-                                let offset = (dist / 5.0).min(40.0).max(10.0);
+                                let offset = (dist / 5.0).clamp(10.0, 40.0);
                                 let cx = (sx + tx)/2.0 + nx * offset;
                                 let cy = (sy + ty)/2.0 + ny * offset;
 
@@ -729,20 +698,20 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
 
                         return;
                     }
-                if *is_dragging_touch_move.borrow() {
-                    if let Some(t) = e.touches().get(0) {
-                        *did_move_touch_move.borrow_mut() = true;
-                        let (lx, ly) = *last_mouse_touch_move.borrow();
-                        let (cx, cy) = (t.client_x() as f64, t.client_y() as f64);
-                        let dx = cx - lx;
-                        let dy = cy - ly;
-
-                        *offset_x_ref_touch.borrow_mut() += dx;
-                        *offset_y_ref_touch.borrow_mut() += dy;
-                        offset_x_touch.set(*offset_x_ref_touch.borrow());
-                        offset_y_touch.set(*offset_y_ref_touch.borrow());
-                        *last_mouse_touch_move.borrow_mut() = (cx, cy);
-                    }
+                if *is_dragging_touch_move.borrow()
+                    && let Some(t) = e.touches().get(0)
+                {
+                    *did_move_touch_move.borrow_mut() = true;
+                    let (lx, ly) = *last_mouse_touch_move.borrow();
+                    let (cx, cy) = (t.client_x() as f64, t.client_y() as f64);
+                    let dx = cx - lx;
+                    let dy = cy - ly;
+
+                    *offset_x_ref_touch.borrow_mut() += dx;
+                    *offset_y_ref_touch.borrow_mut() += dy;
+                    offset_x_touch.set(*offset_x_ref_touch.borrow());
+                    offset_y_touch.set(*offset_y_ref_touch.borrow());
+                    *last_mouse_touch_move.borrow_mut() = (cx, cy);
                 }
             }) as Box<dyn FnMut(_)>);
 
@@ -756,37 +725,37 @@ pub fn canvas_graph(props: &CanvasGraphProps) -> Html {
                 e.prevent_default();
                 *is_dragging_touch_end.borrow_mut() = false;
                 *initial_pinch_distance_end.borrow_mut() = None;
-                if !*did_move_touch_end.borrow() {
-                    if let Some(t) = e.changed_touches().get(0) {
-                        let rect = canvas_for_tap.get_bounding_client_rect();
-                        let sx = t.client_x() as f64 - rect.left();
-                        let sy = t.client_y() as f64 - rect.top();
-
-                        let ctx = canvas_for_tap
-                            .get_context("2d")
-                            .unwrap()
-                            .unwrap()
-                            .dyn_into::<CanvasRenderingContext2d>()
-                            .unwrap();
+                if !*did_move_touch_end.borrow()
+                    && let Some(t) = e.changed_touches().get(0)
+                {
+                    let rect = canvas_for_tap.get_bounding_client_rect();
+                    let sx = t.client_x() as f64 - rect.left();
+                    let sy = t.client_y() as f64 - rect.top();
+
+                    let ctx = canvas_for_tap
+                        .get_context("2d")
+                        .unwrap()
+                        .unwrap()
+                        .dyn_into::<CanvasRenderingContext2d>()
+                        .unwrap();
 
-                        let inv = ctx.get_transform().unwrap().inverse();
-                        let graph_x = inv.a() * sx + inv.c() * sy + inv.e();
-                        let graph_y = inv.b() * sx + inv.d() * sy + inv.f();
-
-                        let mut found = None;
-                        for (idx, node) in graph_for_tap.nodes.iter().enumerate() {
-                            let nx = node.attributes.x / 10.0;
-                            let ny = -node.attributes.y / 10.0;
-                            let r  = (node.attributes.size as f64).log2() / 2.0;
-                            let dx = graph_x - nx;
-                            let dy = graph_y - ny;
-                            if dx*dx + dy*dy <= r*r {
-                                found = Some(idx);
-                                break;
-                            }
+                    let inv = ctx.get_transform().unwrap().inverse();
+                    let graph_x = inv.a() * sx + inv.c() * sy + inv.e();
+                    let graph_y = inv.b() * sx + inv.d() * sy + inv.f();
+
+                    let mut found = None;
+                    for (idx, node) in graph_for_tap.nodes.iter().enumerate() {
+                        let nx = node.attributes.x / 10.0;
+                        let ny = -node.attributes.y / 10.0;
+                        let r  = (node.attributes.size as f64).log2() / 2.0;
+                        let dx = graph_x - nx;
+                        let dy = graph_y - ny;
+                        if dx*dx + dy*dy <= r*r {
+                            found = Some(idx);
+                            break;
                         }
-                        sel_state_touch.set(found);
                     }
+                    sel_state_touch.set(found);
                 }
             }) as Box<dyn FnMut(_)>);
 